@@ -0,0 +1,98 @@
+//! Benchmarks for reading the apple arguments, over a large synthetic set of them.
+//!
+//! Run with `cargo bench --features bench-util,no-ctor`, adding `cache` to compare
+//! repeated lookups with the lookup cache, or `unsync-read` to see what skipping the
+//! `Acquire` fence saves on each read of the arguments.
+
+use appleargs::{apple_args, apple_args_os, env, raw_apple_args, AppleArgsCursor};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use std::os::raw::c_char;
+use std::ptr;
+
+/// Comfortably more than the loader ever passes, so differences show up.
+const COUNT: usize = 1024;
+
+fn init() {
+    let args = appleargs::testing::synthetic_args(COUNT);
+    let mut applep: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+    applep.push(ptr::null());
+
+    // Safety: `applep` is a null-terminated array of nul-terminated strings, and
+    // nothing has read the arguments yet.
+    unsafe { appleargs::init(applep.as_ptr()) };
+}
+
+fn iterators(c: &mut Criterion) {
+    init();
+    assert_eq!(apple_args().len(), COUNT);
+
+    c.bench_function("apple_args", |b| {
+        b.iter(|| apple_args().map(str::len).sum::<usize>())
+    });
+    c.bench_function("apple_args_os", |b| {
+        b.iter(|| apple_args_os().map(|arg| arg.len()).sum::<usize>())
+    });
+    c.bench_function("apple_vars", |b| b.iter(|| env::apple_vars().count()));
+}
+
+fn loads(c: &mut Criterion) {
+    init();
+
+    // Every read of the arguments loads the published pointer, which is where
+    // `unsync-read` drops the fence.
+    c.bench_function("raw_apple_args", |b| {
+        b.iter(|| black_box(raw_apple_args()).len())
+    });
+
+    // Scanning the arguments repeatedly, either refetching them for each scan or
+    // rewinding one cursor.
+    c.bench_function("rescan refetch", |b| {
+        b.iter(|| {
+            (0..16)
+                .map(|_| apple_args_os().map(|arg| arg.len()).sum::<usize>())
+                .sum::<usize>()
+        })
+    });
+    c.bench_function("rescan cursor", |b| {
+        let mut cursor = AppleArgsCursor::new();
+        b.iter(|| {
+            (0..16)
+                .map(|_| {
+                    cursor.reset();
+                    cursor.by_ref().map(|arg| arg.len()).sum::<usize>()
+                })
+                .sum::<usize>()
+        })
+    });
+}
+
+fn lookups(c: &mut Criterion) {
+    init();
+
+    // Lookups search from the back, so the first argument is the slowest to find.
+    c.bench_function("apple_var first", |b| {
+        b.iter(|| env::apple_var(black_box("executable_path")))
+    });
+    c.bench_function("apple_var last", |b| {
+        b.iter(|| env::apple_var(black_box("synthetic_key_1022")))
+    });
+    c.bench_function("apple_var missing", |b| {
+        b.iter(|| env::apple_var(black_box("missing")))
+    });
+
+    // `var_in` never uses the cache, so this is the scan a cache hit above skips.
+    c.bench_function("var_in first", |b| {
+        b.iter(|| env::var_in(raw_apple_args(), black_box("executable_path")))
+    });
+}
+
+fn collections(c: &mut Criterion) {
+    init();
+
+    c.bench_function("apple_vars_btreemap", |b| b.iter(env::apple_vars_btreemap));
+    c.bench_function("capture owned", |b| b.iter(env::AppleEnvOwned::capture));
+}
+
+criterion_group!(benches, iterators, loads, lookups, collections);
+criterion_main!(benches);
@@ -112,14 +112,24 @@ fn install_app_to_simulator() {
         .check_status();
 }
 
-fn run_app_on_simulator(other_args: &[&str]) {
+fn run_app_on_simulator(crate_name: &str, other_args: &[&str]) {
     println!("Running app");
-    let output = Command::new("xcrun")
+    let container = Command::new("xcrun")
         .arg("simctl")
-        .arg("launch")
-        .arg("--console")
+        .arg("get_app_container")
         .arg("booted")
         .arg(BUNDLE_ID)
+        .output()
+        .unwrap();
+    assert!(container.status.success(), "failed to find the installed app");
+    let container = String::from_utf8(container.stdout).unwrap();
+
+    // `launch` doesn't report how the app exited, but `spawn` exits with its status.
+    let output = Command::new("xcrun")
+        .arg("simctl")
+        .arg("spawn")
+        .arg("booted")
+        .arg(Path::new(container.trim()).join(crate_name))
         .args(other_args)
         .output()
         .unwrap();
@@ -127,19 +137,6 @@ fn run_app_on_simulator(other_args: &[&str]) {
     println!("stdout --\n{}\n", String::from_utf8_lossy(&output.stdout));
     println!("stderr --\n{}\n", String::from_utf8_lossy(&output.stderr));
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let failed = stdout
-        .lines()
-        .find(|l| l.contains("FAILED"))
-        .map(|l| l.contains("FAILED"))
-        .unwrap_or(false);
-
-    let passed = stdout
-        .lines()
-        .find(|l| l.contains("test result: ok"))
-        .map(|l| l.contains("test result: ok"))
-        .unwrap_or(false);
-
     println!("Shutting down simulator");
     Command::new("xcrun")
         .arg("simctl")
@@ -147,8 +144,8 @@ fn run_app_on_simulator(other_args: &[&str]) {
         .arg(SIM_NAME)
         .check_status();
 
-    if !(passed && !failed) {
-        panic!("tests didn't pass");
+    if !output.status.success() {
+        panic!("tests didn't pass: {}", output.status);
     }
 }
 
@@ -179,11 +176,11 @@ fn main() {
     }
 
     let test_binary_path = Path::new(&args[1]);
-    let crate_name = test_binary_path.file_name().unwrap();
+    let crate_name = test_binary_path.file_name().unwrap().to_str().unwrap();
     let test_binary_args: Vec<&str> = args.iter().skip(2).map(String::as_str).collect();
 
-    package_as_simulator_app(crate_name.to_str().unwrap(), test_binary_path);
+    package_as_simulator_app(crate_name, test_binary_path);
     start_simulator();
     install_app_to_simulator();
-    run_app_on_simulator(&test_binary_args);
+    run_app_on_simulator(crate_name, &test_binary_args);
 }
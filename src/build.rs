@@ -0,0 +1,157 @@
+//! Building apple argument arrays, in the same layout the loader passes them.
+//!
+//! The kernel builds the apple arguments of every new process itself, so there's no way
+//! to hand a custom array to a child through `posix_spawn` or the `execve` family: they
+//! only take `argv` and `envp`. These are instead for code which reads an `applep`
+//! array, like `init` with the `no-ctor` feature, a custom loader or
+//! trampoline, or tests of either.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+/// A builder for an apple arguments array.
+///
+/// Arguments are kept in the order they were pushed, including duplicate keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppleArgsBuilder {
+    args: Vec<CString>,
+}
+
+impl AppleArgsBuilder {
+    /// Creates a builder without any arguments.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a `key=value` argument.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` contains an `=`, which would move the split, or if either
+    /// contains a nul.
+    pub fn push(&mut self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> &mut Self {
+        let key = key.as_ref();
+        assert!(
+            !key.contains(&b'='),
+            "apple argument keys can't contain an `=`"
+        );
+
+        self.push_raw([key, b"=", value.as_ref()].concat())
+    }
+
+    /// Adds an argument as is, such as a token which isn't a `key=value` pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arg` contains a nul.
+    pub fn push_raw(&mut self, arg: impl Into<Vec<u8>>) -> &mut Self {
+        let arg = CString::new(arg).expect("apple arguments can't contain a nul");
+        self.args.push(arg);
+        self
+    }
+
+    /// Returns the arguments as owned C strings, in order.
+    pub fn into_cstrings(self) -> Vec<CString> {
+        self.args
+    }
+
+    /// Returns the arguments laid out as an `applep` array.
+    pub fn into_applep(self) -> Applep {
+        let mut ptrs: Vec<*const c_char> = self.args.iter().map(|arg| arg.as_ptr()).collect();
+        ptrs.push(core::ptr::null());
+
+        Applep {
+            _args: self.args,
+            ptrs,
+        }
+    }
+}
+
+/// An owned apple arguments array, as built by [`AppleArgsBuilder::into_applep`].
+#[derive(Debug)]
+pub struct Applep {
+    // The pointers point into these, which never move once built.
+    _args: Vec<CString>,
+    ptrs: Vec<*const c_char>,
+}
+
+impl Applep {
+    /// Returns a pointer to the null-terminated array of pointers to nul-terminated
+    /// strings, like the `applep` the loader passes.
+    ///
+    /// The pointer, and every string it points to, is only valid for as long as this
+    /// array is alive. Passing it to something that keeps it around afterwards, instead
+    /// of copying it like `init` does, is undefined behavior.
+    pub fn as_ptr(&self) -> *const *const c_char {
+        self.ptrs.as_ptr()
+    }
+
+    /// Returns how many arguments are in the array, not counting the null terminator.
+    pub fn len(&self) -> usize {
+        self.ptrs.len() - 1
+    }
+
+    /// Returns `true` if the array holds no arguments.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn layout() {
+        let mut builder = AppleArgsBuilder::new();
+        builder
+            .push("executable_path", "/bin/ls")
+            .push_raw("token")
+            .push(b"th_port", b"0x103")
+            .push("empty", "");
+
+        let expected: [&[u8]; 4] = [
+            b"executable_path=/bin/ls",
+            b"token",
+            b"th_port=0x103",
+            b"empty=",
+        ];
+        assert_eq!(
+            builder.clone().into_cstrings(),
+            expected.map(|s| CString::new(s).unwrap())
+        );
+
+        let applep = builder.into_applep();
+        assert_eq!(applep.len(), 4);
+
+        let mut found = Vec::new();
+        let mut p = applep.as_ptr();
+        // Safety: `p` walks a null-terminated array of valid C strings, which `applep`
+        // keeps alive.
+        unsafe {
+            while !p.read().is_null() {
+                found.push(CStr::from_ptr(p.read()).to_bytes());
+                p = p.add(1);
+            }
+        }
+        assert_eq!(found, expected);
+
+        let empty = AppleArgsBuilder::new().into_applep();
+        assert!(empty.is_empty());
+        // Safety: Even an empty array has its null terminator.
+        assert!(unsafe { empty.as_ptr().read() }.is_null());
+    }
+
+    #[test]
+    #[should_panic = "can't contain an `=`"]
+    fn key_with_eq() {
+        AppleArgsBuilder::new().push("a=b", "c");
+    }
+
+    #[test]
+    #[should_panic = "can't contain a nul"]
+    fn arg_with_nul() {
+        AppleArgsBuilder::new().push_raw(&b"a\0b"[..]);
+    }
+}
@@ -0,0 +1,1411 @@
+//! Lookups over the `key=value` shaped apple arguments.
+//!
+//! Most apple arguments are formatted like environment variables, so this module
+//! offers an API shaped like [`std::env`](mod@std::env) for reading them.
+//!
+//! If a key appears more than once, lookups return the last value.
+
+use crate::sys::args_slice;
+use crate::{args_slice_iter, str_from_slice, DebugArg, Remaining};
+use core::iter::FusedIterator;
+use core::str::Utf8Error;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{CStr, CString, NulError, OsStr, OsString};
+use std::os::unix::prelude::OsStrExt;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "cache")]
+mod cache;
+mod owned;
+pub use owned::{AppleEnvOwned, Diff};
+
+#[cfg(feature = "clap")]
+mod cli;
+#[cfg(feature = "clap")]
+pub use cli::with_clap_defaults;
+
+#[cfg(feature = "figment")]
+mod provider;
+#[cfg(feature = "figment")]
+pub use provider::AppleArgsProvider;
+
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "serde")]
+pub use de::{from_apple_args, Error};
+
+/// The error type for apple argument lookups that return UTF-8 strings.
+///
+/// `'a` is the lifetime of the arguments searched, which is `'static` for the current
+/// process' arguments and only shorter for lookups like [`var_in`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarError<'a> {
+    /// The requested key was not present in the apple arguments.
+    NotPresent,
+    /// The key was present, but its value was not valid UTF-8.
+    NotUnicode(&'a OsStr),
+}
+
+impl core::fmt::Display for VarError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            VarError::NotPresent => f.write_str("apple argument not found"),
+            VarError::NotUnicode(v) => write!(f, "apple argument was not valid unicode: {v:?}"),
+        }
+    }
+}
+
+impl std::error::Error for VarError<'_> {}
+
+/// Maps [`VarError::NotPresent`] to [`NotFound`](std::io::ErrorKind::NotFound) and
+/// [`VarError::NotUnicode`] to [`InvalidData`](std::io::ErrorKind::InvalidData).
+impl From<VarError<'static>> for std::io::Error {
+    fn from(err: VarError<'static>) -> Self {
+        let kind = match err {
+            VarError::NotPresent => std::io::ErrorKind::NotFound,
+            VarError::NotUnicode(_) => std::io::ErrorKind::InvalidData,
+        };
+
+        std::io::Error::new(kind, err)
+    }
+}
+
+/// A type which can be used as the key of an apple argument lookup.
+///
+/// Every lookup in this crate takes its key as one of these, so the same key works for
+/// all of them. This is implemented for the usual string, byte string, and path types,
+/// including [`OsStr`], which can't implement `AsRef<[u8]>`.
+pub trait VarKey {
+    /// Returns the raw bytes of the key.
+    fn key_bytes(&self) -> &[u8];
+}
+
+impl<T: VarKey + ?Sized> VarKey for &T {
+    fn key_bytes(&self) -> &[u8] {
+        (**self).key_bytes()
+    }
+}
+
+macro_rules! impl_var_key {
+    ($($ty:ty => |$key:ident| $bytes:expr,)*) => {
+        $(
+            impl VarKey for $ty {
+                #[inline]
+                fn key_bytes(&self) -> &[u8] {
+                    let $key = self;
+                    $bytes
+                }
+            }
+        )*
+    };
+}
+
+impl_var_key! {
+    str => |key| key.as_bytes(),
+    String => |key| key.as_bytes(),
+    [u8] => |key| key,
+    Vec<u8> => |key| key,
+    OsStr => |key| key.as_bytes(),
+    OsString => |key| key.as_bytes(),
+    Path => |key| key.as_os_str().as_bytes(),
+    PathBuf => |key| key.as_os_str().as_bytes(),
+}
+
+impl<const N: usize> VarKey for [u8; N] {
+    #[inline]
+    fn key_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Returns the value of the apple argument `key` as a UTF-8 string.
+///
+/// The key can be any [`VarKey`], which includes [`OsStr`] as well as the usual
+/// string types:
+///
+/// ```
+/// use std::ffi::OsString;
+///
+/// let key = OsString::from("executable_path");
+/// if let Ok(path) = appleargs::env::apple_var(&key) {
+///     println!("launched from {path}");
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`VarError::NotPresent`] if `key` isn't present, or [`VarError::NotUnicode`]
+/// if its value isn't valid UTF-8.
+///
+/// # Caching
+///
+/// With the `cache` feature, the result for each key is remembered once the arguments
+/// have been captured, so repeated lookups of a key skip searching the arguments and
+/// validating its value. Every distinct key ever looked up, including missing ones,
+/// stays in the cache for the rest of the program, and each lookup takes a lock, so
+/// this only pays off for programs looking up the same few keys very often.
+#[inline]
+pub fn apple_var(key: impl VarKey) -> Result<&'static str, VarError<'static>> {
+    let key = key.key_bytes();
+
+    #[cfg(feature = "cache")]
+    if crate::init_state() != crate::InitState::NotRun {
+        return cache::get_or_insert(key, || decode_var(apple_getenv(key)));
+    }
+
+    decode_var(apple_getenv(key))
+}
+
+/// Returns the value of the apple argument `key`, or `None` if it isn't present.
+#[inline]
+pub fn apple_var_os(key: impl VarKey) -> Option<&'static OsStr> {
+    apple_getenv(key.key_bytes()).map(OsStr::from_bytes)
+}
+
+/// Like [`apple_var`], but searches `env` instead of the current process' arguments.
+///
+/// `env` holds one argument per element, without its nul terminator, such as the
+/// arguments forwarded from another process. Lookups follow the same rules as
+/// [`apple_var`], so the last value of a repeated key wins. This never uses the
+/// `cache` feature's cache, and `env` may hold any bytes, including nuls.
+///
+/// ```
+/// let forwarded: &[&[u8]] = &[b"executable_path=/bin/ls", b"th_port=259"];
+/// assert_eq!(appleargs::env::var_in(forwarded, "th_port"), Ok("259"));
+/// ```
+///
+/// # Errors
+///
+/// Returns [`VarError::NotPresent`] if `key` isn't present in `env`, or
+/// [`VarError::NotUnicode`] if its value isn't valid UTF-8.
+#[inline]
+pub fn var_in<'a>(env: &[&'a [u8]], key: impl VarKey) -> Result<&'a str, VarError<'a>> {
+    decode_var(getenv_any_in(env, key.key_bytes()))
+}
+
+/// Like [`apple_var_os`], but searches `env` instead of the current process' arguments.
+///
+/// See [`var_in`] for what `env` should hold.
+#[inline]
+pub fn var_os_in<'a>(env: &[&'a [u8]], key: impl VarKey) -> Option<&'a OsStr> {
+    getenv_any_in(env, key.key_bytes()).map(OsStr::from_bytes)
+}
+
+/// Returns the value of the apple argument `key`, or the result of `default` if it isn't
+/// present or isn't valid UTF-8.
+///
+/// `default` is only called when needed, like with [`Result::unwrap_or_else`].
+#[inline]
+pub fn apple_var_or_else(key: impl VarKey, default: impl FnOnce() -> &'static str) -> &'static str {
+    apple_var(key).unwrap_or_else(|_| default())
+}
+
+/// Returns the value of the apple argument `key`, or the result of `default` if it isn't
+/// present.
+///
+/// `default` is only called when needed, like with [`Option::unwrap_or_else`].
+#[inline]
+pub fn apple_var_os_or_else(
+    key: impl VarKey,
+    default: impl FnOnce() -> &'static OsStr,
+) -> &'static OsStr {
+    apple_var_os(key).unwrap_or_else(default)
+}
+
+/// Returns whether the apple argument `key` is present, whatever its value is.
+///
+/// An argument with an empty value, like `key=`, counts as present.
+#[inline]
+pub fn apple_has_var(key: impl VarKey) -> bool {
+    apple_getenv(key.key_bytes()).is_some()
+}
+
+/// Returns the stored key and value of the apple argument `key` as UTF-8 strings.
+///
+/// The key always matches `key` exactly, but is borrowed from the arguments themselves,
+/// so it lives for `'static`.
+///
+/// # Errors
+///
+/// Returns [`VarError::NotPresent`] if `key` isn't present, or [`VarError::NotUnicode`]
+/// if the stored key or its value isn't valid UTF-8.
+#[inline]
+pub fn apple_var_entry(
+    key: impl VarKey,
+) -> Result<(&'static str, &'static str), VarError<'static>> {
+    let (key, value) = entry_in(args_slice(), key.key_bytes()).ok_or(VarError::NotPresent)?;
+    // The key was matched against a `&[u8]`, so it may not be UTF-8 either.
+    Ok((decode_var(Some(key))?, decode_var(Some(value))?))
+}
+
+/// Returns the stored key and value of the apple argument `key`, or `None` if it
+/// isn't present.
+///
+/// See [`apple_var_entry`] for why returning the key is useful.
+#[inline]
+pub fn apple_var_os_entry(key: impl VarKey) -> Option<(&'static OsStr, &'static OsStr)> {
+    entry_in(args_slice(), key.key_bytes())
+        .map(|(k, v)| (OsStr::from_bytes(k), OsStr::from_bytes(v)))
+}
+
+/// Returns the raw bytes of the apple argument `key`'s value, or `None` if it
+/// isn't present.
+#[inline]
+pub fn apple_var_bytes(key: impl VarKey) -> Option<&'static [u8]> {
+    apple_getenv(key.key_bytes())
+}
+
+/// Like [`apple_var_bytes`], but for a key which is already a C string, such as one
+/// handed back by a C API.
+///
+/// The key's nul terminator isn't part of the comparison.
+#[inline]
+pub fn apple_var_cstr(key: &CStr) -> Option<&'static [u8]> {
+    apple_getenv(key.to_bytes())
+}
+
+/// Returns the value of the apple argument `key` parsed as a hex number, or `None` if
+/// it isn't present or isn't one.
+///
+/// The value may have a `0x` prefix, like the kernel writes them, or be bare hex
+/// digits. Numbers which don't fit in a `u64` are rejected.
+#[inline]
+pub fn apple_var_u64_hex(key: impl VarKey) -> Option<u64> {
+    apple_getenv(key.key_bytes()).and_then(parse_u64_hex)
+}
+
+/// Returns the value of the apple argument `key` decoded from hex into bytes, or
+/// `None` if it isn't present or isn't hex.
+///
+/// The value may have a `0x` prefix, and must have an even number of digits, like the
+/// `executable_cdhash` argument.
+#[cfg(feature = "hex")]
+pub fn apple_var_hex(key: impl VarKey) -> Option<Vec<u8>> {
+    apple_getenv(key.key_bytes()).and_then(decode_hex)
+}
+
+/// Returns the value of `key` from the apple arguments, falling back to the process'
+/// real environment variables if it isn't there.
+///
+/// The apple argument takes precedence whenever it's present and valid UTF-8, which
+/// lets launch-time apple arguments override normal configuration. Otherwise this is
+/// [`std::env::var`], which allocates, with errors mapped to `None`.
+pub fn var_with_fallback(key: &str) -> Option<Cow<'static, str>> {
+    var_with_fallback_in(args_slice(), key)
+}
+
+fn var_with_fallback_in<T: AsRef<[u8]>>(
+    args: &'static [T],
+    key: &str,
+) -> Option<Cow<'static, str>> {
+    match decode_var(getenv_in(args, key.as_bytes())) {
+        Ok(value) => Some(Cow::Borrowed(value)),
+        Err(_) => std::env::var(key).ok().map(Cow::Owned),
+    }
+}
+
+/// Returns the fields of the apple argument `key`, split at every `sep`, or `None` if it
+/// isn't present.
+///
+/// This is for list values like `malloc_entropy` and `main_stack`, which use `,`. Like
+/// [`slice::split`], an empty value has a single empty field, and repeated or trailing
+/// separators give empty fields too.
+///
+/// ```
+/// if let Some(fields) = appleargs::env::apple_var_list("malloc_entropy", b',') {
+///     println!("{} entropy values", fields.count());
+/// }
+/// ```
+#[inline]
+pub fn apple_var_list(
+    key: impl VarKey,
+    sep: u8,
+) -> Option<impl DoubleEndedIterator<Item = &'static [u8]> + FusedIterator + Clone> {
+    apple_getenv(key.key_bytes()).map(|value| split_list(value, sep))
+}
+
+/// Returns every value of the apple argument `key` joined together by `sep`, or `None`
+/// if it isn't present.
+///
+/// Unlike [`apple_var`], which only returns the last value of a repeated key, this is
+/// for consumers which treat repeated keys as a list. Values are joined in the order
+/// they appear, and any invalid UTF-8 in them is replaced, as this allocates anyway.
+pub fn apple_var_joined(key: impl VarKey, sep: &str) -> Option<String> {
+    joined_in(args_slice(), key.key_bytes(), sep)
+}
+
+/// Like [`apple_var`], but ignores ASCII whitespace surrounding keys and values.
+///
+/// The returned value has its surrounding whitespace trimmed. This is only useful for
+/// apple arguments which have been padded by something, as the kernel never does so.
+#[inline]
+pub fn apple_var_trimmed(key: impl VarKey) -> Result<&'static str, VarError<'static>> {
+    decode_var(getenv_trimmed_in(args_slice(), key.key_bytes()))
+}
+
+/// Returns the keys of every apple argument whose value is exactly `value`.
+///
+/// Keys which aren't valid UTF-8 are skipped. Keys are yielded in the order they
+/// appear in the apple arguments.
+#[inline]
+pub fn apple_keys_for_value(value: &str) -> impl Iterator<Item = &'static str> + '_ {
+    keys_for_value_in(args_slice(), value.as_bytes())
+        .filter_map(|key| core::str::from_utf8(key).ok())
+}
+
+/// Returns the keys of every apple argument whose value is exactly `value`.
+///
+/// Keys are yielded in the order they appear in the apple arguments.
+#[inline]
+pub fn apple_keys_for_value_os(value: &OsStr) -> impl Iterator<Item = &'static OsStr> + '_ {
+    keys_for_value_in(args_slice(), value.as_bytes()).map(OsStr::from_bytes)
+}
+
+/// An apple argument, as yielded by [`apple_tokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Token {
+    /// An argument without an `=`.
+    Raw(&'static OsStr),
+    /// A `key=value` argument, split into its key and value at the first `=`.
+    Pair(&'static OsStr, &'static OsStr),
+}
+
+/// Returns an iterator over every apple argument of the current process, as either a
+/// raw token or a `key=value` pair.
+///
+/// This is [`apple_entries`] without the UTF-8 requirement, and with an enum telling
+/// the two kinds of arguments apart, for consumers which handle both in one pass.
+/// When the items already have the same type, the iterators in this crate can just be
+/// [`chain`](Iterator::chain)ed instead:
+///
+/// ```
+/// use appleargs::env::{apple_tokens, apple_vars_os, Token};
+///
+/// // Every raw argument, followed by just the keys.
+/// let tokens = appleargs::apple_args_os().chain(apple_vars_os().map(|(key, _)| key));
+/// println!("{} tokens and keys", tokens.count());
+///
+/// for token in apple_tokens() {
+///     match token {
+///         Token::Raw(arg) => println!("token {arg:?}"),
+///         Token::Pair(key, value) => println!("{key:?} is {value:?}"),
+///     }
+/// }
+/// ```
+#[inline]
+pub fn apple_tokens(
+) -> impl DoubleEndedIterator<Item = Token> + ExactSizeIterator + FusedIterator + Clone {
+    tokens_in(args_slice())
+}
+
+fn tokens_in(
+    args: &'static [&'static [u8]],
+) -> impl DoubleEndedIterator<Item = Token> + ExactSizeIterator + FusedIterator + Clone {
+    args.iter().map(|arg| match os_pair(arg) {
+        Some((k, v)) => Token::Pair(k, v),
+        None => Token::Raw(OsStr::from_bytes(arg)),
+    })
+}
+
+/// An iterator over the `key=value` apple arguments of the process.
+///
+/// Arguments without an `=` are skipped. This iterator will panic if any of the
+/// pairs are not valid UTF-8.
+#[derive(Clone)]
+pub struct AppleVars {
+    inner: Remaining<&'static [u8]>,
+}
+
+impl AppleVars {
+    /// Returns an iterator over no arguments.
+    ///
+    /// Unlike [`apple_vars`], this is a `const fn`, so it can be used to initialize a
+    /// `const` or `static`, or as a placeholder in a default value.
+    #[inline]
+    pub const fn empty() -> Self {
+        Self {
+            inner: Remaining::EMPTY,
+        }
+    }
+}
+
+impl core::fmt::Debug for AppleVars {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs = self.inner.clone().filter_map(|arg| split_kv(arg));
+
+        f.debug_list()
+            .entries(pairs.map(|(k, v)| (DebugArg(k), DebugArg(v))))
+            .finish()
+    }
+}
+
+impl Iterator for AppleVars {
+    type Item = (&'static str, &'static str);
+
+    #[inline]
+    #[track_caller]
+    fn next(&mut self) -> Option<Self::Item> {
+        // Not `find_map`, so the panic location stays the caller's.
+        for arg in self.inner.by_ref() {
+            if let Some(pair) = str_pair(arg) {
+                return Some(pair);
+            }
+        }
+
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl DoubleEndedIterator for AppleVars {
+    #[inline]
+    #[track_caller]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while let Some(arg) = self.inner.next_back() {
+            if let Some(pair) = str_pair(arg) {
+                return Some(pair);
+            }
+        }
+
+        None
+    }
+}
+
+impl FusedIterator for AppleVars {}
+
+/// Returns an iterator over the `key=value` apple arguments of the current process,
+/// split into their keys and values.
+///
+/// See [`apple_args`](crate::apple_args) for the guarantees about ordering and contents.
+#[inline]
+pub fn apple_vars() -> AppleVars {
+    AppleVars {
+        inner: args_slice_iter(),
+    }
+}
+
+/// Returns an iterator over the `key=value` apple arguments of the current process,
+/// which reports invalid UTF-8 instead of panicking.
+///
+/// Each item is either the decoded pair, or the error along with the whole raw argument
+/// that failed to decode. Arguments without an `=` are skipped, like in [`apple_vars`].
+#[inline]
+pub fn try_apple_vars() -> impl DoubleEndedIterator<Item = TryVar> + FusedIterator + Clone {
+    try_vars_in(args_slice())
+}
+
+type TryVar = Result<(&'static str, &'static str), (Utf8Error, &'static [u8])>;
+
+fn try_vars_in(
+    args: &'static [&'static [u8]],
+) -> impl DoubleEndedIterator<Item = TryVar> + FusedIterator + Clone {
+    args.iter()
+        .filter_map(|&arg| match core::str::from_utf8(arg) {
+            Ok(s) => s.split_once('=').map(Ok),
+            Err(e) => split_kv(arg).map(|_| Err((e, arg))),
+        })
+}
+
+/// Returns an iterator over every apple argument of the current process, split into
+/// its key and value if it has one.
+///
+/// `key=value` arguments are yielded as `(key, Some(value))`, and arguments without an
+/// `=` as `(argument, None)`, so unlike [`apple_vars`] nothing is skipped. This iterator
+/// will panic if any of the arguments are not valid UTF-8.
+#[inline]
+pub fn apple_entries(
+) -> impl DoubleEndedIterator<Item = Entry> + ExactSizeIterator + FusedIterator + Clone {
+    entries_in(args_slice())
+}
+
+type Entry = (&'static str, Option<&'static str>);
+
+fn entries_in(
+    args: &'static [&'static [u8]],
+) -> impl DoubleEndedIterator<Item = Entry> + ExactSizeIterator + FusedIterator + Clone {
+    args.iter().map(|arg| {
+        let arg = str_from_slice(arg);
+        match arg.split_once('=') {
+            Some((k, v)) => (k, Some(v)),
+            None => (arg, None),
+        }
+    })
+}
+
+/// Returns an iterator over the `key=value` apple arguments of the current process,
+/// split like [`apple_vars`], along with the whole argument each pair came from.
+///
+/// The argument is the exact bytes the loader passed, for code which needs to hash or
+/// reproduce it without joining the key and value back together. Arguments without
+/// an `=` are skipped. This iterator will panic if any of the pairs are not valid UTF-8.
+#[inline]
+pub fn apple_entries_raw() -> impl DoubleEndedIterator<Item = RawEntry> + FusedIterator + Clone {
+    entries_raw_in(args_slice())
+}
+
+type RawEntry = (&'static str, &'static str, &'static [u8]);
+
+fn entries_raw_in(
+    args: &'static [&'static [u8]],
+) -> impl DoubleEndedIterator<Item = RawEntry> + FusedIterator + Clone {
+    args.iter()
+        .filter_map(|arg| str_pair(arg).map(|(k, v)| (k, v, *arg)))
+}
+
+/// An iterator over the `key=value` apple arguments of the process.
+///
+/// Arguments without an `=` are skipped. This iterator does not check that any
+/// pair is valid UTF-8.
+#[derive(Clone)]
+pub struct AppleVarsOs {
+    inner: Remaining<&'static [u8]>,
+}
+
+impl AppleVarsOs {
+    /// Returns an iterator over no arguments.
+    ///
+    /// Unlike [`apple_vars_os`], this is a `const fn`, so it can be used to initialize a
+    /// `const` or `static`, or as a placeholder in a default value.
+    #[inline]
+    pub const fn empty() -> Self {
+        Self {
+            inner: Remaining::EMPTY,
+        }
+    }
+}
+
+impl core::fmt::Debug for AppleVarsOs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl Iterator for AppleVarsOs {
+    type Item = (&'static OsStr, &'static OsStr);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.find_map(|arg| os_pair(arg))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl DoubleEndedIterator for AppleVarsOs {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().rev().find_map(|arg| os_pair(arg))
+    }
+}
+
+impl FusedIterator for AppleVarsOs {}
+
+/// Returns an iterator over the `key=value` apple arguments of the current process,
+/// split into their keys and values.
+///
+/// See [`apple_args_os`](crate::apple_args_os) for the guarantees about ordering and
+/// contents.
+#[inline]
+pub fn apple_vars_os() -> AppleVarsOs {
+    AppleVarsOs {
+        inner: args_slice_iter(),
+    }
+}
+
+/// Collects the `key=value` apple arguments of the current process into a map sorted
+/// by key.
+///
+/// If a key appears more than once, the last value wins, like the other lookups in
+/// this module.
+///
+/// # Panics
+///
+/// Panics if any of the pairs are not valid UTF-8, like [`apple_vars`].
+#[track_caller]
+pub fn apple_vars_btreemap() -> BTreeMap<&'static str, &'static str> {
+    // Not `collect`, so the panic location stays the caller's.
+    let mut map = BTreeMap::new();
+    for (key, value) in apple_vars() {
+        map.insert(key, value);
+    }
+
+    map
+}
+
+/// Collects the `key=value` apple arguments of the current process into a map sorted
+/// by key.
+///
+/// If a key appears more than once, the last value wins, like the other lookups in
+/// this module.
+pub fn apple_vars_btreemap_os() -> BTreeMap<&'static OsStr, &'static OsStr> {
+    apple_vars_os().collect()
+}
+
+/// Looks up the values of several apple arguments at once.
+///
+/// This only scans the arguments once, so it's cheaper than calling [`apple_var`] for
+/// each key. If a key appears more than once, the last value wins, and keys which
+/// aren't present, or whose last value isn't valid UTF-8, don't appear in the result.
+pub fn apple_vars_subset(keys: &[&str]) -> HashMap<&'static str, &'static str> {
+    subset_str_in(args_slice(), keys)
+}
+
+/// Looks up the values of several apple arguments at once.
+///
+/// See [`apple_vars_subset`] for the details.
+pub fn apple_vars_subset_os(keys: &[&OsStr]) -> HashMap<&'static OsStr, &'static OsStr> {
+    subset_in(args_slice(), keys)
+        .map(|(k, v)| (OsStr::from_bytes(k), OsStr::from_bytes(v)))
+        .collect()
+}
+
+fn subset_str_in<'a, T: AsRef<[u8]>>(args: &'a [T], keys: &[&str]) -> HashMap<&'a str, &'a str> {
+    // Find the last value of each key before decoding, so an invalid last value hides
+    // the key instead of uncovering an earlier one.
+    let found: HashMap<_, _> = subset_in(args, keys).collect();
+    found
+        .into_iter()
+        .filter_map(|(k, v)| Some((core::str::from_utf8(k).ok()?, core::str::from_utf8(v).ok()?)))
+        .collect()
+}
+
+fn subset_in<'a: 'k, 'k, T: AsRef<[u8]>, K: VarKey>(
+    args: &'a [T],
+    keys: &'k [K],
+) -> impl Iterator<Item = (&'a [u8], &'a [u8])> + 'k {
+    args.iter()
+        .filter_map(|arg| split_kv(arg.as_ref()))
+        .filter(move |(k, _)| keys.iter().any(|key| key.key_bytes() == *k))
+}
+
+/// Inserts the `key=value` apple arguments of the current process into `map`.
+///
+/// Apple arguments overwrite any entries already in `map` with the same key, and if a
+/// key appears more than once, the last value wins. Pairs whose key isn't valid UTF-8
+/// are skipped, while invalid UTF-8 in values is replaced.
+pub fn extend_map(map: &mut HashMap<String, String>) {
+    extend_map_in(args_slice(), map)
+}
+
+fn extend_map_in<T: AsRef<[u8]>>(args: &[T], map: &mut HashMap<String, String>) {
+    map.extend(
+        args.iter()
+            .filter_map(|arg| split_kv(arg.as_ref()))
+            .filter_map(|(k, v)| {
+                let key = core::str::from_utf8(k).ok()?;
+                Some((key.to_owned(), String::from_utf8_lossy(v).into_owned()))
+            }),
+    );
+}
+
+/// Inserts the `key=value` apple arguments of the current process into `map`.
+///
+/// Apple arguments overwrite any entries already in `map` with the same key, and if a
+/// key appears more than once, the last value wins.
+pub fn extend_map_os(map: &mut HashMap<OsString, OsString>) {
+    map.extend(apple_vars_os().map(|(k, v)| (k.to_owned(), v.to_owned())));
+}
+
+/// Returns how many apple arguments are `key=value` pairs.
+///
+/// Arguments without an `=` aren't counted, so this is what [`apple_vars`] would
+/// yield without having to iterate it.
+#[inline]
+pub fn count_keys() -> usize {
+    count_keys_in(args_slice())
+}
+
+/// Returns the total length in bytes of every `key=value` apple argument, including
+/// the `=`s but not the nul terminators.
+///
+/// Arguments without an `=` aren't counted, like in [`count_keys`].
+#[inline]
+pub fn total_env_len() -> usize {
+    total_env_len_in(args_slice())
+}
+
+fn total_env_len_in<T: AsRef<[u8]>>(args: &[T]) -> usize {
+    args.iter()
+        .map(AsRef::as_ref)
+        .filter(|arg| split_kv(arg).is_some())
+        .map(<[u8]>::len)
+        .sum()
+}
+
+/// Returns how many apple arguments there are, including ones that aren't
+/// `key=value` pairs.
+#[inline]
+pub fn count_all_args() -> usize {
+    args_slice().len()
+}
+
+/// Copies every apple argument of the current process into a nul-terminated [`CString`],
+/// in order.
+///
+/// Both `key=value` pairs and other arguments are copied exactly as they were passed,
+/// which makes this suitable for rebuilding an apple arguments array to hand to a child
+/// process.
+///
+/// # Errors
+///
+/// Returns an error if an argument contains a nul. Arguments are read up to their nul
+/// terminator by the loader, so that can't happen in practice.
+pub fn to_cstrings() -> Result<Vec<CString>, NulError> {
+    to_cstrings_in(args_slice())
+}
+
+fn to_cstrings_in<T: AsRef<[u8]>>(args: &[T]) -> Result<Vec<CString>, NulError> {
+    args.iter().map(|arg| CString::new(arg.as_ref())).collect()
+}
+
+/// Splits every `key=value` argument in `input` into its key and value, using
+/// the same rules as the rest of this module.
+///
+/// Arguments without an `=` are skipped. This accepts any bytes, including nuls,
+/// which apple arguments can't contain, and never panics. It exists for fuzzing and
+/// for parsing arguments captured from somewhere other than the current process.
+///
+/// The pairs are plain tuples, so they sort and compare by key, then by value, with
+/// both compared bytewise. That makes them usable as is in a `BTreeSet` or with
+/// [`slice::sort`].
+pub fn parse_env_pairs<'a>(input: &[&'a [u8]]) -> Vec<(&'a [u8], &'a [u8])> {
+    input.iter().filter_map(|arg| split_kv_any(arg)).collect()
+}
+
+#[track_caller]
+fn str_pair(arg: &&'static [u8]) -> Option<(&'static str, &'static str)> {
+    // Decoding the whole argument keeps the index in the panic message.
+    split_kv(arg)?;
+    str_from_slice(arg).split_once('=')
+}
+
+fn os_pair(arg: &[u8]) -> Option<(&OsStr, &OsStr)> {
+    split_kv(arg).map(|(k, v)| (OsStr::from_bytes(k), OsStr::from_bytes(v)))
+}
+
+fn count_keys_in<T: AsRef<[u8]>>(args: &[T]) -> usize {
+    args.iter()
+        .filter(|arg| split_kv(arg.as_ref()).is_some())
+        .count()
+}
+
+fn decode_var(value: Option<&[u8]>) -> Result<&str, VarError<'_>> {
+    let value = value.ok_or(VarError::NotPresent)?;
+    core::str::from_utf8(value).map_err(|_| VarError::NotUnicode(OsStr::from_bytes(value)))
+}
+
+/// Splits an apple argument into its key and value at the first `=`.
+///
+/// Returns `None` for arguments which aren't `key=value` pairs.
+pub(crate) fn split_kv(arg: &[u8]) -> Option<(&[u8], &[u8])> {
+    debug_assert!(!arg.contains(&0), "apple arguments never contain a nul");
+
+    split_kv_any(arg)
+}
+
+/// [`split_kv`], for bytes which didn't come from the loader.
+fn split_kv_any(arg: &[u8]) -> Option<(&[u8], &[u8])> {
+    let eq = arg.iter().position(|&b| b == b'=')?;
+    Some((&arg[..eq], &arg[eq + 1..]))
+}
+
+/// Splits a list value into its fields at every `sep`.
+pub(crate) fn split_list(
+    value: &[u8],
+    sep: u8,
+) -> impl DoubleEndedIterator<Item = &[u8]> + FusedIterator + Clone {
+    value.split(move |&b| b == sep)
+}
+
+/// Looks up the value of `key` in `args`.
+///
+/// If a key appears multiple times, the last one wins.
+pub(crate) fn getenv_in<'a, T: AsRef<[u8]>>(args: &'a [T], key: &[u8]) -> Option<&'a [u8]> {
+    entry_in(args, key).map(|(_, v)| v)
+}
+
+/// Like [`getenv_in`], for arguments which didn't come from the loader.
+fn getenv_any_in<'a>(args: &[&'a [u8]], key: &[u8]) -> Option<&'a [u8]> {
+    args.iter().rev().find_map(|arg| match split_kv_any(arg) {
+        Some((k, v)) if k == key => Some(v),
+        _ => None,
+    })
+}
+
+/// Like [`getenv_in`], but also returns the key as stored in `args`.
+fn entry_in<'a, T: AsRef<[u8]>>(args: &'a [T], key: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    args.iter()
+        .rev()
+        .find_map(|arg| match split_kv(arg.as_ref()) {
+            Some((k, v)) if k == key => Some((k, v)),
+            _ => None,
+        })
+}
+
+/// Like [`getenv_in`], but ignores surrounding ASCII whitespace on keys and values.
+pub(crate) fn getenv_trimmed_in<'a, T: AsRef<[u8]>>(args: &'a [T], key: &[u8]) -> Option<&'a [u8]> {
+    let key = trim_ascii(key);
+
+    args.iter()
+        .rev()
+        .find_map(|arg| match split_kv(arg.as_ref()) {
+            Some((k, v)) if trim_ascii(k) == key => Some(trim_ascii(v)),
+            _ => None,
+        })
+}
+
+fn joined_in<T: AsRef<[u8]>>(args: &[T], key: &[u8], sep: &str) -> Option<String> {
+    let mut values = args.iter().filter_map(|arg| match split_kv(arg.as_ref()) {
+        Some((k, v)) if k == key => Some(String::from_utf8_lossy(v)),
+        _ => None,
+    });
+
+    let mut joined = values.next()?.into_owned();
+    for value in values {
+        joined.push_str(sep);
+        joined.push_str(&value);
+    }
+
+    Some(joined)
+}
+
+fn keys_for_value_in<'a: 'v, 'v, T: AsRef<[u8]>>(
+    args: &'a [T],
+    value: &'v [u8],
+) -> impl Iterator<Item = &'a [u8]> + 'v {
+    args.iter()
+        .filter_map(move |arg| match split_kv(arg.as_ref()) {
+            Some((k, v)) if v == value => Some(k),
+            _ => None,
+        })
+}
+
+fn trim_ascii(mut bytes: &[u8]) -> &[u8] {
+    while let [first, rest @ ..] = bytes {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+
+    while let [rest @ .., last] = bytes {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+
+    bytes
+}
+
+fn strip_hex_prefix(value: &[u8]) -> &[u8] {
+    value.strip_prefix(b"0x").unwrap_or(value)
+}
+
+fn parse_u64_hex(value: &[u8]) -> Option<u64> {
+    parse_hex_digits(strip_hex_prefix(value))
+}
+
+#[cfg(feature = "hex")]
+fn decode_hex(value: &[u8]) -> Option<Vec<u8>> {
+    let digits = strip_hex_prefix(value);
+
+    // An odd number of digits fails the length check.
+    let mut out = vec![0; digits.len() / 2];
+    decode_hex_into(digits, &mut out)?;
+    Some(out)
+}
+
+/// Parses 1 to 16 bare hex digits into a number.
+pub(crate) fn parse_hex_digits(digits: &[u8]) -> Option<u64> {
+    if digits.is_empty() || digits.len() > 16 {
+        return None;
+    }
+
+    digits
+        .iter()
+        .try_fold(0u64, |acc, &b| Some((acc << 4) | u64::from(hex_digit(b)?)))
+}
+
+/// Decodes pairs of bare hex digits into `out`, which must be half as long as `digits`.
+pub(crate) fn decode_hex_into(digits: &[u8], out: &mut [u8]) -> Option<()> {
+    if digits.len() != out.len() * 2 {
+        return None;
+    }
+
+    for (byte, pair) in out.iter_mut().zip(digits.chunks_exact(2)) {
+        *byte = (hex_digit(pair[0])? << 4) | hex_digit(pair[1])?;
+    }
+
+    Some(())
+}
+
+pub(crate) fn hex_digit(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Looks up the value of `key` in the current process' apple arguments.
+pub(crate) fn apple_getenv(key: &[u8]) -> Option<&'static [u8]> {
+    getenv_in(args_slice(), key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ptr;
+
+    #[test]
+    fn split_and_lookup() {
+        assert_eq!(split_kv(b"a=b=c"), Some((&b"a"[..], &b"b=c"[..])));
+        assert_eq!(split_kv(b"a="), Some((&b"a"[..], &b""[..])));
+        assert_eq!(split_kv(b"token"), None);
+
+        let args: &[&[u8]] = &[b"a=1", b"token", b"ab=2", b"a=3"];
+        assert_eq!(getenv_in(args, b"a"), Some(&b"3"[..]));
+        assert_eq!(getenv_in(args, b"ab"), Some(&b"2"[..]));
+        assert_eq!(getenv_in(args, b"token"), None);
+        assert_eq!(getenv_in(args, b"b"), None);
+    }
+
+    #[test]
+    fn entry_lookup() {
+        let args: &[&[u8]] = &[b"k\xff=1", b"a=2", b"k\xff=3"];
+
+        let (key, value) = entry_in(args, b"k\xff").unwrap();
+        assert!(ptr::eq(key, &args[2][..2]));
+        assert_eq!(value, b"3");
+        assert_eq!(entry_in(args, b"a"), Some((&b"a"[..], &b"2"[..])));
+        assert_eq!(entry_in(args, b"k"), None);
+    }
+
+    #[test]
+    fn list_values() {
+        fn fields(value: &[u8], sep: u8) -> Vec<&[u8]> {
+            split_list(value, sep).collect()
+        }
+
+        assert_eq!(fields(b"0x1,0x2,0x3", b','), [&b"0x1"[..], b"0x2", b"0x3"]);
+        assert_eq!(fields(b"a;b;;c", b';'), [&b"a"[..], b"b", b"", b"c"]);
+        assert_eq!(fields(b"a;b", b','), [&b"a;b"[..]]);
+        assert_eq!(fields(b"", b','), [&b""[..]]);
+        assert_eq!(split_list(b"x,y", b',').next_back(), Some(&b"y"[..]));
+    }
+
+    #[test]
+    fn custom_env() {
+        let env: &'static [&'static [u8]] = &[b"a=1", b"bad=\xff", b"token", b"a=2"];
+
+        assert_eq!(var_in(env, "a"), Ok("2"));
+        assert_eq!(var_in(env, OsStr::new("a")), Ok("2"));
+        assert_eq!(var_in(env, "token"), Err(VarError::NotPresent));
+        assert_eq!(
+            var_in(env, "bad"),
+            Err(VarError::NotUnicode(OsStr::from_bytes(b"\xff")))
+        );
+        assert!(ptr::eq(
+            var_os_in(env, "bad").unwrap().as_bytes(),
+            &env[1][4..]
+        ));
+        assert_eq!(var_os_in(env, "missing"), None);
+        assert_eq!(var_os_in(&[], "a"), None);
+
+        // Arguments from elsewhere needn't be `'static`, or free of nuls.
+        let owned = [b"na\0me=v\0".to_vec(), b"bad=\xff".to_vec()];
+        let env: Vec<&[u8]> = owned.iter().map(Vec::as_slice).collect();
+        assert_eq!(var_in(&env, "na\0me"), Ok("v\0"));
+        assert_eq!(var_os_in(&env, "na\0me"), Some(OsStr::new("v\0")));
+        assert_eq!(
+            var_in(&env, "bad"),
+            Err(VarError::NotUnicode(OsStr::from_bytes(b"\xff")))
+        );
+    }
+
+    #[test]
+    fn io_errors() {
+        let err = std::io::Error::from(VarError::NotPresent);
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+        let err = std::io::Error::from(VarError::NotUnicode(OsStr::from_bytes(b"\xff")));
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err
+            .to_string()
+            .starts_with("apple argument was not valid unicode"));
+    }
+
+    #[test]
+    fn key_types() {
+        fn bytes(key: impl VarKey) -> Vec<u8> {
+            key.key_bytes().to_vec()
+        }
+
+        let expected = b"executable_path".to_vec();
+        assert_eq!(bytes("executable_path"), expected);
+        assert_eq!(bytes(String::from("executable_path")), expected);
+        assert_eq!(bytes(&b"executable_path"[..]), expected);
+        assert_eq!(bytes(b"executable_path"), expected);
+        assert_eq!(bytes(&expected), expected);
+        assert_eq!(bytes(OsStr::new("executable_path")), expected);
+        assert_eq!(bytes(OsString::from("executable_path")), expected);
+        assert_eq!(bytes(Path::new("executable_path")), expected);
+        assert_eq!(bytes(PathBuf::from("executable_path")), expected);
+    }
+
+    #[test]
+    fn lookups_accept_any_key() {
+        let key = OsString::from("APPLEARGS_TEST_MISSING");
+
+        assert_eq!(apple_var_os(Path::new("APPLEARGS_TEST_MISSING")), None);
+        assert_eq!(var_os_in(&[b"k=v"], "k"), Some(OsStr::new("v")));
+        assert!(!apple_has_var(&key));
+        assert_eq!(
+            apple_var_entry(b"APPLEARGS_TEST_MISSING"),
+            Err(VarError::NotPresent)
+        );
+        assert_eq!(apple_var_os_entry("APPLEARGS_TEST_MISSING"), None);
+        assert_eq!(apple_var_bytes(key.as_os_str()), None);
+        assert_eq!(
+            apple_var_u64_hex(String::from("APPLEARGS_TEST_MISSING")),
+            None
+        );
+        assert!(apple_var_list(&b"APPLEARGS_TEST_MISSING"[..], b',').is_none());
+        assert_eq!(
+            apple_var_joined(PathBuf::from("APPLEARGS_TEST_MISSING"), ","),
+            None
+        );
+        assert_eq!(apple_var_trimmed(&key), Err(VarError::NotPresent));
+        assert_eq!(
+            apple_var_os_or_else(b"APPLEARGS_TEST_MISSING".to_vec(), || OsStr::new("d")),
+            "d"
+        );
+    }
+
+    #[test]
+    fn trimmed_lookup() {
+        let args: &[&[u8]] = &[b"padded=  value \t", b" spaced_key =x", b"exact=y"];
+
+        assert_eq!(getenv_trimmed_in(args, b"padded"), Some(&b"value"[..]));
+        assert_eq!(getenv_trimmed_in(args, b"spaced_key"), Some(&b"x"[..]));
+        assert_eq!(getenv_trimmed_in(args, b" exact "), Some(&b"y"[..]));
+        assert_eq!(getenv_trimmed_in(args, b"missing"), None);
+
+        // The default lookup stays byte-exact.
+        assert_eq!(getenv_in(args, b"padded"), Some(&b"  value \t"[..]));
+        assert_eq!(getenv_in(args, b"spaced_key"), None);
+    }
+
+    #[test]
+    fn joined_values() {
+        let args: &[&[u8]] = &[b"k=a", b"other=x", b"k=b", b"k", b"k=\xffc"];
+
+        assert_eq!(joined_in(args, b"k", ":").as_deref(), Some("a:b:\u{fffd}c"));
+        assert_eq!(joined_in(args, b"other", ":").as_deref(), Some("x"));
+        assert_eq!(joined_in(args, b"missing", ":"), None);
+        assert_eq!(joined_in(&[b"k="], b"k", ":").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn hex_values() {
+        let args: &[&[u8]] = &[
+            b"prefixed=0xdeadBEEF",
+            b"bare=ff",
+            b"big=0x1ffffffffffffffff",
+        ];
+
+        assert_eq!(
+            getenv_in(args, b"prefixed").and_then(parse_u64_hex),
+            Some(0xdead_beef)
+        );
+        assert_eq!(getenv_in(args, b"bare").and_then(parse_u64_hex), Some(0xff));
+        assert_eq!(getenv_in(args, b"big").and_then(parse_u64_hex), None);
+        assert_eq!(parse_u64_hex(b"0x"), None);
+        assert_eq!(parse_u64_hex(b"0xfg"), None);
+        assert_eq!(parse_u64_hex(b""), None);
+    }
+
+    #[test]
+    #[cfg(feature = "hex")]
+    fn hex_bytes() {
+        assert_eq!(decode_hex(b"0xacD9"), Some(vec![0xac, 0xd9]));
+        assert_eq!(decode_hex(b"acd984"), Some(vec![0xac, 0xd9, 0x84]));
+        assert_eq!(decode_hex(b""), Some(vec![]));
+        assert_eq!(decode_hex(b"0xacd"), None);
+        assert_eq!(decode_hex(b"zz"), None);
+    }
+
+    #[test]
+    fn reverse_lookup() {
+        let args: &[&[u8]] = &[b"a=/bin/ls", b"b=/bin/sh", b"/bin/ls", b"c=/bin/ls"];
+
+        let keys: Vec<_> = keys_for_value_in(args, b"/bin/ls").collect();
+        assert_eq!(keys, [&b"a"[..], &b"c"[..]]);
+        assert_eq!(keys_for_value_in(args, b"/bin/zsh").count(), 0);
+    }
+
+    #[test]
+    fn pair_splitting() {
+        let args: &[&[u8]] = &[b"a=1", b"token", b"b=", b"=c", b"d==", b"", b"e"];
+
+        let pairs: Vec<_> = args.iter().filter_map(|arg| os_pair(arg)).collect();
+        assert_eq!(
+            pairs,
+            [("a", "1"), ("b", ""), ("", "c"), ("d", "=")]
+                .map(|(k, v)| (OsStr::new(k), OsStr::new(v)))
+        );
+        assert_eq!(count_keys_in(args), 4);
+        assert_eq!(count_keys_in::<&[u8]>(&[]), 0);
+    }
+
+    #[test]
+    fn sorted_pairs() {
+        let args: &[&[u8]] = &[b"b=1", b"a=2", b"token", b"a=1", b"B=3", b"ab="];
+        let mut pairs = parse_env_pairs(args);
+        pairs.sort();
+
+        assert_eq!(
+            pairs,
+            [("B", "3"), ("a", "1"), ("a", "2"), ("ab", ""), ("b", "1")]
+                .map(|(k, v)| (k.as_bytes(), v.as_bytes()))
+        );
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn parse_env_pairs_never_panics(input: Vec<Vec<u8>>) {
+            let input: Vec<&[u8]> = input.iter().map(Vec::as_slice).collect();
+            let pairs = parse_env_pairs(&input);
+
+            let kv_args = input.iter().filter(|arg| arg.contains(&b'='));
+            proptest::prop_assert_eq!(pairs.len(), kv_args.clone().count());
+            for ((k, v), arg) in pairs.into_iter().zip(kv_args) {
+                proptest::prop_assert!(!k.contains(&b'='));
+                proptest::prop_assert_eq!([k, b"=", v].concat(), arg.to_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn debug_invalid_utf8() {
+        let args: &'static [&'static [u8]] = &[b"a=\xff", b"b"];
+        let vars = AppleVars {
+            inner: Remaining(args),
+        };
+
+        assert_eq!(format!("{vars:?}"), r#"[("a", b"\xff")]"#);
+    }
+
+    #[test]
+    fn const_empty() {
+        const VARS: AppleVars = AppleVars::empty();
+
+        assert_eq!(VARS.count(), 0);
+        assert_eq!(AppleVarsOs::empty().count(), 0);
+        assert_eq!(AppleVarsOs::empty().next_back(), None);
+    }
+
+    #[test]
+    fn tokens() {
+        let args: &'static [&'static [u8]] = &[b"a=1", b"token", b"b=\xff", b"\xff"];
+        let os = |bytes: &'static [u8]| OsStr::from_bytes(bytes);
+
+        let mut tokens = tokens_in(args);
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens.next(), Some(Token::Pair(os(b"a"), os(b"1"))));
+        assert_eq!(tokens.next_back(), Some(Token::Raw(os(b"\xff"))));
+        assert_eq!(
+            tokens.collect::<Vec<_>>(),
+            [Token::Raw(os(b"token")), Token::Pair(os(b"b"), os(b"\xff"))]
+        );
+
+        // With matching item types, chaining needs no help.
+        let chained = crate::AppleArgsOs {
+            inner: Remaining(args),
+        }
+        .chain(
+            AppleVarsOs {
+                inner: Remaining(args),
+            }
+            .map(|(key, _)| key),
+        );
+        assert_eq!(
+            chained.collect::<Vec<_>>(),
+            [
+                os(b"a=1"),
+                os(b"token"),
+                os(b"b=\xff"),
+                os(b"\xff"),
+                os(b"a"),
+                os(b"b")
+            ]
+        );
+    }
+
+    #[test]
+    fn entries() {
+        let args: &'static [&'static [u8]] = &[b"a=1", b"token", b"b=", b"=c", b"d=e=f"];
+
+        let mut entries = entries_in(args);
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries.next(), Some(("a", Some("1"))));
+        assert_eq!(entries.next(), Some(("token", None)));
+        assert_eq!(entries.next_back(), Some(("d", Some("e=f"))));
+        assert_eq!(
+            entries.collect::<Vec<_>>(),
+            [("b", Some("")), ("", Some("c"))]
+        );
+        assert_eq!(entries_in(&[]).next(), None);
+    }
+
+    #[test]
+    fn raw_entries() {
+        let args: &'static [&'static [u8]] = &[b"a=1", b"token", b"b=c=d", b"e="];
+
+        let entries: Vec<_> = entries_raw_in(args).collect();
+        assert_eq!(
+            entries,
+            [
+                ("a", "1", &b"a=1"[..]),
+                ("b", "c=d", b"b=c=d"),
+                ("e", "", b"e=")
+            ]
+        );
+        for (&(_, _, raw), source) in entries.iter().zip([args[0], args[2], args[3]]) {
+            assert!(ptr::eq(raw, source));
+        }
+        assert_eq!(entries_raw_in(args).next_back().map(|(k, ..)| k), Some("e"));
+    }
+
+    #[test]
+    fn fallible_vars() {
+        let args: &'static [&'static [u8]] = &[b"a=1", b"token", b"b=\xff", b"c=3"];
+
+        let mut vars = try_vars_in(args);
+        assert_eq!(vars.next(), Some(Ok(("a", "1"))));
+
+        let (err, arg) = vars.next().unwrap().unwrap_err();
+        assert_eq!(arg, b"b=\xff");
+        assert_eq!(err.valid_up_to(), 2);
+
+        assert_eq!(vars.next(), Some(Ok(("c", "3"))));
+        assert_eq!(vars.next(), None);
+        assert_eq!(try_vars_in(args).rev().count(), 3);
+    }
+
+    #[test]
+    fn subset_lookup() {
+        let args: &[&[u8]] = &[b"a=1", b"b=\xff", b"c=3", b"a=4", b"d=5"];
+        let keys = ["a", "b", "c", "missing"];
+
+        let found: HashMap<_, _> = subset_in(args, &keys).collect();
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[&b"a"[..]], b"4");
+        assert_eq!(found[&b"b"[..]], b"\xff");
+        assert_eq!(found[&b"c"[..]], b"3");
+        assert_eq!(subset_in::<_, &str>(args, &[]).count(), 0);
+
+        let found = subset_str_in(args, &keys);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found["a"], "4");
+        assert_eq!(found["c"], "3");
+
+        // Like `var_in`, an invalid last value doesn't fall back to an earlier valid one.
+        let args: &'static [&'static [u8]] = &[b"a=1", b"a=\xff"];
+        assert_eq!(subset_str_in(args, &["a"]), HashMap::new());
+        assert!(matches!(var_in(args, "a"), Err(VarError::NotUnicode(_))));
+    }
+
+    #[test]
+    fn map_overlay() {
+        let args: &[&[u8]] = &[b"a=1", b"b=\xffx", b"\xfe=skipped", b"token", b"a=3"];
+
+        let mut map = HashMap::from([
+            ("a".to_owned(), "default".to_owned()),
+            ("c".to_owned(), "kept".to_owned()),
+        ]);
+        extend_map_in(args, &mut map);
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["a"], "3");
+        assert_eq!(map["b"], "\u{fffd}x");
+        assert_eq!(map["c"], "kept");
+    }
+
+    #[test]
+    fn sorted_maps() {
+        let args: &'static [&'static [u8]] = &[b"b=1", b"token", b"a=2", b"c=3", b"b=4"];
+
+        let map: BTreeMap<_, _> = AppleVars {
+            inner: Remaining(args),
+        }
+        .collect();
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            [("a", "2"), ("b", "4"), ("c", "3")]
+        );
+
+        let map: BTreeMap<_, _> = AppleVarsOs {
+            inner: Remaining(args),
+        }
+        .collect();
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(map[OsStr::new("b")], "4");
+    }
+
+    #[test]
+    fn env_length() {
+        let args: &[&[u8]] = &[b"a=1", b"token", b"b=", b"\xff=\xfe"];
+
+        assert_eq!(total_env_len_in(args), 8);
+        assert_eq!(total_env_len_in::<&[u8]>(&[b"token"]), 0);
+    }
+
+    #[test]
+    fn owned_cstrings() {
+        let args: &[&[u8]] = &[b"executable_path=/bin/ls", b"token", b"bad=\xff"];
+
+        let owned = to_cstrings_in(args).unwrap();
+        assert_eq!(owned.len(), 3);
+        for (c, arg) in owned.iter().zip(args) {
+            assert_eq!(c.as_bytes(), *arg);
+            assert_eq!(c.as_bytes_with_nul().last(), Some(&0));
+        }
+
+        let err = to_cstrings_in::<&[u8]>(&[b"a=1", b"b=\0"]).unwrap_err();
+        assert_eq!(err.nul_position(), 2);
+    }
+
+    #[test]
+    fn real_env_fallback() {
+        // Falling back to a variable which is set is tested in `tests/real_env.rs`, as
+        // setting it here could race with other tests reading the environment.
+        let args: &[&[u8]] = &[b"APPLEARGS_TEST_FALLBACK=from_apple", b"bad=\xff"];
+        assert_eq!(
+            var_with_fallback_in(args, "APPLEARGS_TEST_FALLBACK"),
+            Some(Cow::Borrowed("from_apple"))
+        );
+        assert_eq!(var_with_fallback_in(args, "bad"), None);
+        assert_eq!(var_with_fallback_in(args, "APPLEARGS_TEST_MISSING"), None);
+    }
+
+    #[test]
+    fn var_decoding() {
+        assert_eq!(decode_var(Some(b"ok")), Ok("ok"));
+        assert_eq!(decode_var(None), Err(VarError::NotPresent));
+        assert_eq!(
+            decode_var(Some(b"\xff")),
+            Err(VarError::NotUnicode(OsStr::from_bytes(b"\xff")))
+        );
+    }
+}
@@ -0,0 +1,52 @@
+//! Memoized [`apple_var`](super::apple_var) lookups, for the `cache` feature.
+
+use super::VarError;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+type Lookup = Result<&'static str, VarError<'static>>;
+
+/// Every key looked up so far, and what it found.
+static CACHE: Mutex<Option<HashMap<Box<[u8]>, Lookup>>> = Mutex::new(None);
+
+/// Returns the cached result for `key`, or caches the one from `lookup`.
+///
+/// This must only be called after the arguments were captured, as they can't change
+/// after that.
+pub(super) fn get_or_insert(key: &[u8], lookup: impl FnOnce() -> Lookup) -> Lookup {
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    let cache = cache.get_or_insert_with(HashMap::new);
+
+    if let Some(&found) = cache.get(key) {
+        return found;
+    }
+
+    let found = lookup();
+    cache.insert(key.into(), found);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memoizes() {
+        let mut calls = 0;
+        let mut lookup = |key: &[u8]| {
+            get_or_insert(key, || {
+                calls += 1;
+                Ok("value")
+            })
+        };
+
+        assert_eq!(lookup(b"appleargs_cache_a"), Ok("value"));
+        assert_eq!(lookup(b"appleargs_cache_a"), Ok("value"));
+        assert_eq!(lookup(b"appleargs_cache_b"), Ok("value"));
+        assert_eq!(calls, 2);
+
+        let missing = get_or_insert(b"appleargs_cache_c", || Err(VarError::NotPresent));
+        assert_eq!(missing, Err(VarError::NotPresent));
+        assert_eq!(get_or_insert(b"appleargs_cache_c", || Ok("late")), missing);
+    }
+}
@@ -0,0 +1,79 @@
+//! Feeding apple arguments into `clap`.
+
+use super::getenv_in;
+use crate::sys::args_slice;
+use std::ffi::OsStr;
+use std::os::unix::prelude::OsStrExt;
+
+/// Uses apple arguments as the defaults of `cmd`'s arguments.
+///
+/// Every argument with an [`env`](clap::Arg::env) name gets the apple argument of the
+/// same key as its default value, if one is present. This gives the precedence:
+///
+/// 1. Flags passed on the command line.
+/// 2. Real environment variables.
+/// 3. Apple arguments.
+/// 4. Any default value the argument already had, which is replaced when an apple
+///    argument is present.
+///
+/// # Example
+///
+/// ```no_run
+/// let cmd = clap::Command::new("app")
+///     .arg(clap::Arg::new("path").long("path").env("executable_path"));
+///
+/// let matches = appleargs::env::with_clap_defaults(cmd).get_matches();
+/// ```
+pub fn with_clap_defaults(cmd: clap::Command) -> clap::Command {
+    with_defaults_in(args_slice(), cmd)
+}
+
+fn with_defaults_in<T: AsRef<[u8]>>(args: &'static [T], cmd: clap::Command) -> clap::Command {
+    cmd.mut_args(|arg| {
+        let value = arg
+            .get_env()
+            .and_then(|key| getenv_in(args, key.as_bytes()));
+
+        match value {
+            Some(value) => arg.default_value(OsStr::from_bytes(value)),
+            None => arg,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, Command};
+
+    #[test]
+    fn defaults_from_apple_args() {
+        let args: &[&[u8]] = &[
+            b"APPLEARGS_TEST_CLAP_A=apple_a",
+            b"APPLEARGS_TEST_CLAP_B=apple_b",
+        ];
+        let build = || {
+            let cmd = Command::new("test")
+                .arg(Arg::new("a").long("a").env("APPLEARGS_TEST_CLAP_A"))
+                .arg(Arg::new("b").long("b").env("APPLEARGS_TEST_CLAP_B"))
+                .arg(
+                    Arg::new("c")
+                        .long("c")
+                        .env("APPLEARGS_TEST_CLAP_C")
+                        .default_value("default_c"),
+                )
+                .arg(Arg::new("d").long("d"));
+
+            with_defaults_in(args, cmd)
+        };
+
+        let matches = build().get_matches_from(["test", "--b", "cli_b"]);
+        let get = |id| matches.get_one::<String>(id).map(String::as_str);
+        assert_eq!(get("a"), Some("apple_a"));
+        assert_eq!(get("b"), Some("cli_b"));
+        assert_eq!(get("c"), Some("default_c"));
+        assert_eq!(get("d"), None);
+        // Precedence over the real environment is tested in `tests/real_env.rs`, as
+        // setting a variable here could race with other tests reading the environment.
+    }
+}
@@ -0,0 +1,242 @@
+//! Deserializing apple arguments into user types, in the style of `envy`.
+
+use super::split_kv;
+use crate::sys::args_slice;
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use std::collections::HashMap;
+
+/// The error type returned by [`from_apple_args`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(String);
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: core::fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// Deserializes a `T` from the `key=value` apple arguments of the current process.
+///
+/// Each key is matched against a field of `T`, so `#[serde(rename)]` and friends work
+/// as usual. Fields of type `Option` are `None` when their key isn't present. If a key
+/// appears multiple times, the last value is used.
+///
+/// Only flat structs are supported for now, where each field is a string or something
+/// parseable from one (integers, floats, `bool`s, and unit enum variants). Booleans
+/// may be written as `true`/`false` or `1`/`0`.
+///
+/// # Errors
+///
+/// Returns an error if a required field is missing, or if a value couldn't be parsed
+/// into its field's type.
+///
+/// # Example
+///
+/// ```no_run
+/// #[derive(serde::Deserialize)]
+/// struct Launch {
+///     executable_path: String,
+///     #[serde(rename = "arm64e_abi")]
+///     abi: Option<String>,
+/// }
+///
+/// let launch: Launch = appleargs::env::from_apple_args().unwrap();
+/// println!("launched from {}", launch.executable_path);
+/// ```
+pub fn from_apple_args<T: DeserializeOwned>() -> Result<T, Error> {
+    from_args(args_slice())
+}
+
+fn from_args<T: DeserializeOwned, A: AsRef<[u8]>>(args: &[A]) -> Result<T, Error> {
+    let mut vars = HashMap::new();
+
+    for (key, value) in args.iter().filter_map(|arg| split_kv(arg.as_ref())) {
+        // A key that isn't UTF-8 couldn't match a field anyway.
+        if let Ok(key) = core::str::from_utf8(key) {
+            vars.insert(key, Value(value));
+        }
+    }
+
+    T::deserialize(de::value::MapDeserializer::new(vars.into_iter()))
+}
+
+struct Value<'de>(&'de [u8]);
+
+impl<'de> Value<'de> {
+    fn as_str(&self) -> Result<&'de str, Error> {
+        core::str::from_utf8(self.0)
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Bytes(self.0), &"a UTF-8 string"))
+    }
+
+    fn parse<T: core::str::FromStr>(&self, expected: &str) -> Result<T, Error> {
+        let s = self.as_str()?;
+        s.parse()
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(s), &expected))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident($expected:literal),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse($expected)?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Value<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.as_str()?)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            b"1" => visitor.visit_bool(true),
+            b"0" => visitor.visit_bool(false),
+            _ => visitor.visit_bool(self.parse("a boolean")?),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8("an 8-bit integer"),
+        deserialize_i16 => visit_i16("a 16-bit integer"),
+        deserialize_i32 => visit_i32("a 32-bit integer"),
+        deserialize_i64 => visit_i64("a 64-bit integer"),
+        deserialize_u8 => visit_u8("an unsigned 8-bit integer"),
+        deserialize_u16 => visit_u16("an unsigned 16-bit integer"),
+        deserialize_u32 => visit_u32("an unsigned 32-bit integer"),
+        deserialize_u64 => visit_u64("an unsigned 64-bit integer"),
+        deserialize_f32 => visit_f32("a number"),
+        deserialize_f64 => visit_f64("a number"),
+        deserialize_char => visit_char("a single character"),
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.as_str()?.into_deserializer())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // Unknown keys shouldn't be rejected for not being UTF-8.
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Abi {
+        Os,
+        All,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        executable_path: String,
+        #[serde(rename = "th_port")]
+        thread_port: u32,
+        ptrauth_disabled: Option<bool>,
+        arm64e_abi: Option<Abi>,
+        missing: Option<String>,
+    }
+
+    #[test]
+    fn flat_struct() {
+        let args: &[&[u8]] = &[
+            b"executable_path=/bin/old",
+            b"th_port=",
+            b"unrelated=\xff",
+            b"token",
+            b"executable_path=/bin/ls",
+            b"th_port=259",
+            b"ptrauth_disabled=1",
+            b"arm64e_abi=os",
+        ];
+
+        let config: Config = from_args(args).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                executable_path: "/bin/ls".into(),
+                thread_port: 259,
+                ptrauth_disabled: Some(true),
+                arm64e_abi: Some(Abi::Os),
+                missing: None,
+            }
+        );
+
+        let args: &[&[u8]] = &[b"executable_path=/bin/ls", b"th_port=1", b"arm64e_abi=all"];
+        let config: Config = from_args(args).unwrap();
+        assert_eq!(config.arm64e_abi, Some(Abi::All));
+        assert_eq!(config.ptrauth_disabled, None);
+    }
+
+    #[test]
+    fn errors() {
+        let args: &[&[u8]] = &[b"executable_path=/bin/ls"];
+        assert!(from_args::<Config, _>(args).is_err());
+
+        let args: &[&[u8]] = &[b"executable_path=/bin/ls", b"th_port=port"];
+        assert_eq!(
+            from_args::<Config, _>(args).unwrap_err().to_string(),
+            r#"invalid value: string "port", expected an unsigned 32-bit integer"#
+        );
+
+        let args: &[&[u8]] = &[
+            b"executable_path=/bin/ls",
+            b"th_port=1",
+            b"ptrauth_disabled=2",
+        ];
+        assert_eq!(
+            from_args::<Config, _>(args).unwrap_err().to_string(),
+            r#"invalid value: string "2", expected a boolean"#
+        );
+
+        let args: &[&[u8]] = &[b"executable_path=\xff", b"th_port=1"];
+        assert!(from_args::<Config, _>(args).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! An owned copy of the apple arguments' `key=value` pairs.
+
+use super::{split_kv, VarKey};
+use crate::sys::args_slice;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::os::unix::prelude::OsStrExt;
+
+/// An owned snapshot of the `key=value` apple arguments.
+///
+/// Unlike the rest of this crate, nothing in here borrows from the process' global
+/// copy of the arguments, so it can be modified freely or kept around in other types.
+/// Pairs are kept in the order they appeared in, including any duplicate keys.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct AppleEnvOwned {
+    pairs: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl AppleEnvOwned {
+    /// Copies the `key=value` apple arguments of the current process.
+    pub fn capture() -> Self {
+        Self::from_args(args_slice())
+    }
+
+    /// Parses a flat block of apple arguments, laid out like the kernel writes them
+    /// onto the stack, such as one saved from a crash dump.
+    ///
+    /// The block is a list of arguments, each followed by a nul byte, and ends with
+    /// an empty argument, so the last one is followed by two nul bytes:
+    ///
+    /// ```text
+    /// executable_path=/bin/ls\0th_port=0x103\0\0
+    /// ```
+    ///
+    /// Anything after the terminating empty argument is ignored. A block which is cut
+    /// short keeps all of its arguments up to the last nul byte, dropping the unfinished
+    /// one. Like [`capture`](Self::capture), arguments which aren't `key=value` pairs
+    /// are skipped.
+    pub fn from_raw_block(bytes: &[u8]) -> Self {
+        let args: Vec<&[u8]> = bytes
+            .split_inclusive(|&b| b == 0)
+            .take_while(|arg| matches!(arg, [_, .., 0]))
+            .map(|arg| &arg[..arg.len() - 1])
+            .collect();
+
+        Self::from_args(&args)
+    }
+
+    pub(crate) fn from_args<T: AsRef<[u8]>>(args: &[T]) -> Self {
+        let pairs = args
+            .iter()
+            .filter_map(|arg| split_kv(arg.as_ref()))
+            .map(|(k, v)| (k.to_vec(), v.to_vec()))
+            .collect();
+
+        Self { pairs }
+    }
+
+    /// Returns the value of `key`, or `None` if it isn't present.
+    ///
+    /// If the key appears more than once, the last value is returned.
+    pub fn get(&self, key: impl VarKey) -> Option<&[u8]> {
+        let key = key.key_bytes();
+        self.pairs
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_slice())
+    }
+
+    /// Sets the value of `key`, replacing every existing value for it.
+    pub fn insert(&mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        let key = key.into();
+        self.pairs.retain(|(k, _)| *k != key);
+        self.pairs.push((key, value.into()));
+    }
+
+    /// Keeps only the pairs for which `f` returns `true`.
+    pub fn retain(&mut self, mut f: impl FnMut(&[u8], &[u8]) -> bool) {
+        self.pairs.retain(|(k, v)| f(k, v));
+    }
+
+    /// Removes the pairs known to hold security sensitive values, making the snapshot
+    /// safer to log or attach to bug reports.
+    ///
+    /// This removes `stack_guard`, `ptr_munge`, and `malloc_entropy`. Anything else
+    /// is kept, so be careful with custom apple arguments.
+    pub fn without_secrets(mut self) -> Self {
+        const SECRETS: &[&[u8]] = &[b"stack_guard", b"ptr_munge", b"malloc_entropy"];
+
+        self.retain(|k, _| !SECRETS.contains(&k));
+        self
+    }
+
+    /// Compares this snapshot against `other`, treating `self` as the old one.
+    ///
+    /// The comparison is by key, so the order pairs appear in doesn't matter. Like
+    /// [`get`](Self::get), only the last value of a repeated key is compared. Each list
+    /// in the result is sorted by key.
+    pub fn diff<'a>(&'a self, other: &'a Self) -> Diff<'a> {
+        let old = self.as_map();
+        let mut new = other.as_map();
+        let mut diff = Diff::default();
+
+        for (key, old_value) in old {
+            match new.remove(key) {
+                Some(new_value) if new_value != old_value => {
+                    diff.changed.push((key, old_value, new_value))
+                }
+                Some(_) => {}
+                None => diff.removed.push((key, old_value)),
+            }
+        }
+        diff.added.extend(new);
+
+        diff
+    }
+
+    fn as_map(&self) -> BTreeMap<&[u8], &[u8]> {
+        // Later pairs overwrite earlier ones, keeping the last value like `get`.
+        self.iter().collect()
+    }
+
+    /// Returns an iterator over the stored pairs, in order.
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (&[u8], &[u8])> + ExactSizeIterator + Clone {
+        self.pairs.iter().map(|(k, v)| (k.as_slice(), v.as_slice()))
+    }
+
+    /// Returns how many pairs are stored.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns `true` if no pairs are stored.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+/// The differences between two [`AppleEnvOwned`] snapshots, as returned by
+/// [`AppleEnvOwned::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff<'a> {
+    /// The `(key, value)` pairs only in the new snapshot.
+    pub added: Vec<(&'a [u8], &'a [u8])>,
+    /// The `(key, value)` pairs only in the old snapshot.
+    pub removed: Vec<(&'a [u8], &'a [u8])>,
+    /// The `(key, old, new)` values of keys in both snapshots whose value differs.
+    pub changed: Vec<(&'a [u8], &'a [u8], &'a [u8])>,
+}
+
+impl Diff<'_> {
+    /// Returns `true` if the snapshots had the same keys and values.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl core::fmt::Debug for AppleEnvOwned {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.iter()
+                    .map(|(k, v)| (OsStr::from_bytes(k), OsStr::from_bytes(v))),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_queries() {
+        let args: &[&[u8]] = &[b"a=1", b"token", b"b=2", b"a=3", b"c=\xff"];
+        let mut env = AppleEnvOwned::from_args(args);
+
+        assert_eq!(env.len(), 4);
+        assert_eq!(env.get("a"), Some(&b"3"[..]));
+        assert_eq!(env.get(b"c"), Some(&b"\xff"[..]));
+        assert_eq!(env.get("token"), None);
+        assert_eq!(
+            env.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            [&b"a"[..], b"b", b"a", b"c"]
+        );
+
+        env.insert("a", "4");
+        env.insert("d", "5");
+        assert_eq!(env.len(), 4);
+        assert_eq!(env.get("a"), Some(&b"4"[..]));
+        assert_eq!(env.iter().next_back(), Some((&b"d"[..], &b"5"[..])));
+
+        env.retain(|k, v| k != b"b" && v != b"4");
+        assert_eq!(
+            env.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            [&b"c"[..], b"d"]
+        );
+
+        assert!(AppleEnvOwned::default().is_empty());
+        assert_eq!(
+            format!("{:?}", AppleEnvOwned::from_args(&[b"k=v"])),
+            r#"{"k": "v"}"#
+        );
+    }
+
+    #[test]
+    fn snapshot_diff() {
+        let old = AppleEnvOwned::from_args::<&[u8]>(&[b"a=1", b"b=2", b"c=3", b"c=4", b"d=5"]);
+        let new = AppleEnvOwned::from_args::<&[u8]>(&[b"d=5", b"e=6", b"c=4", b"a=9"]);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, [(&b"e"[..], &b"6"[..])]);
+        assert_eq!(diff.removed, [(&b"b"[..], &b"2"[..])]);
+        assert_eq!(diff.changed, [(&b"a"[..], &b"1"[..], &b"9"[..])]);
+        assert!(!diff.is_empty());
+
+        let reordered = AppleEnvOwned::from_args::<&[u8]>(&[b"d=5", b"a=1", b"c=4", b"b=2"]);
+        assert!(old.diff(&reordered).is_empty());
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn raw_blocks() {
+        let block = b"executable_path=/bin/ls\0token\0empty=\0th_port=0x103\0\0stale=1\0\0";
+        let env = AppleEnvOwned::from_raw_block(block);
+
+        assert_eq!(
+            env.iter().collect::<Vec<_>>(),
+            [
+                (&b"executable_path"[..], &b"/bin/ls"[..]),
+                (b"empty", b""),
+                (b"th_port", b"0x103"),
+            ]
+        );
+        let args: &[&[u8]] = &[
+            b"executable_path=/bin/ls",
+            b"token",
+            b"empty=",
+            b"th_port=0x103",
+        ];
+        assert_eq!(env, AppleEnvOwned::from_args(args));
+
+        let truncated = AppleEnvOwned::from_raw_block(b"a=1\0b=2\0c=");
+        assert_eq!(
+            truncated.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            [b"a", b"b"]
+        );
+
+        assert!(AppleEnvOwned::from_raw_block(b"\0a=1\0\0").is_empty());
+        assert!(AppleEnvOwned::from_raw_block(b"").is_empty());
+    }
+
+    #[test]
+    fn secrets_removed() {
+        let args: &[&[u8]] = &[
+            b"executable_path=/bin/ls",
+            b"stack_guard=0xa38b2f9c6e2d1400",
+            b"ptr_munge=0x5f1d3c8a",
+            b"malloc_entropy=0x1,0x2",
+            b"th_port=259",
+            b"stack_guard_extra=1",
+        ];
+        let env = AppleEnvOwned::from_args(args).without_secrets();
+
+        assert_eq!(
+            env.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            [&b"executable_path"[..], b"th_port", b"stack_guard_extra"]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Layering apple arguments into a `figment` configuration.
+
+use super::split_kv;
+use crate::sys::args_slice;
+use figment::value::{Dict, Map, Value};
+use figment::{Error, Metadata, Profile, Provider};
+
+/// A [`figment::Provider`] serving the `key=value` apple arguments of the current
+/// process.
+///
+/// Each pair becomes a top-level string value under its exact key, so only flat
+/// configurations of strings are supported. Keys containing a `.` aren't nested. If a
+/// key appears multiple times, the last value is used, and pairs which aren't valid
+/// UTF-8 are skipped.
+///
+/// Like any provider, what wins depends on how it's combined: values from providers
+/// [`merge`](figment::Figment::merge)d later override earlier ones. Apple arguments are
+/// set by the loader rather than the user, so they usually belong before files and
+/// environment variables:
+///
+/// ```no_run
+/// use figment::{providers::Env, Figment};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Config {
+///     executable_path: String,
+/// }
+///
+/// let config: Config = Figment::from(appleargs::env::AppleArgsProvider::new())
+///     .merge(Env::raw())
+///     .extract()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct AppleArgsProvider {
+    profile: Profile,
+}
+
+impl AppleArgsProvider {
+    /// Creates a provider serving the apple arguments in the default profile.
+    pub fn new() -> Self {
+        Self {
+            profile: Profile::Default,
+        }
+    }
+
+    /// Serves the apple arguments in `profile` instead of the default one.
+    pub fn profile(mut self, profile: impl Into<Profile>) -> Self {
+        self.profile = profile.into();
+        self
+    }
+
+    /// Collects the pairs in `args` into this provider's profile.
+    fn data_in(&self, args: &[&[u8]]) -> Map<Profile, Dict> {
+        let mut dict = Dict::new();
+        for (key, value) in args.iter().filter_map(|arg| split_kv(arg)) {
+            if let (Ok(key), Ok(value)) = (core::str::from_utf8(key), core::str::from_utf8(value)) {
+                dict.insert(key.to_owned(), Value::from(value));
+            }
+        }
+
+        self.profile.collect(dict)
+    }
+}
+
+impl Default for AppleArgsProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for AppleArgsProvider {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("apple arguments")
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, Error> {
+        // Read here instead of in `new`, in case the provider was created early.
+        Ok(self.data_in(args_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use figment::Figment;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        executable_path: String,
+        th_port: String,
+        #[serde(rename = "a.b")]
+        dotted: String,
+        missing: Option<String>,
+    }
+
+    /// Serves `args` through `AppleArgsProvider` instead of the current process' arguments.
+    struct Fixture(AppleArgsProvider, &'static [&'static [u8]]);
+
+    impl Fixture {
+        fn profile(self, profile: &str) -> Self {
+            Self(self.0.profile(profile), self.1)
+        }
+    }
+
+    impl Provider for Fixture {
+        fn metadata(&self) -> Metadata {
+            self.0.metadata()
+        }
+
+        fn data(&self) -> Result<Map<Profile, Dict>, Error> {
+            Ok(self.0.data_in(self.1))
+        }
+    }
+
+    fn provider(args: &'static [&'static [u8]]) -> Fixture {
+        Fixture(AppleArgsProvider::new(), args)
+    }
+
+    #[test]
+    fn extract() {
+        let args: &'static [&'static [u8]] = &[
+            b"executable_path=/bin/old",
+            b"token",
+            b"th_port=0x103",
+            b"bad=\xff",
+            b"a.b=c",
+            b"executable_path=/bin/ls",
+        ];
+
+        let config: Config = Figment::from(provider(args)).extract().unwrap();
+        assert_eq!(
+            config,
+            Config {
+                executable_path: "/bin/ls".into(),
+                th_port: "0x103".into(),
+                dotted: "c".into(),
+                missing: None,
+            }
+        );
+
+        let figment = Figment::from(provider(args).profile("apple"));
+        assert!(figment.extract::<Config>().is_err());
+        assert!(figment.find_value("bad").is_err());
+        assert!(figment.select("apple").extract::<Config>().is_ok());
+    }
+}
@@ -0,0 +1,1615 @@
+//! Typed accessors for apple arguments with a known meaning.
+//!
+//! Everything here is best-effort: the kernel is free to stop passing any of these,
+//! so each accessor returns `None` when its argument is absent or isn't in the
+//! expected format.
+
+use crate::env::{
+    apple_getenv, decode_hex_into, hex_digit, parse_hex_digits, split_kv, split_list, VarKey,
+};
+use std::ffi::{c_void, OsStr};
+use std::os::raw::{c_char, c_int};
+use std::os::unix::prelude::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// The path used to execute the main executable.
+///
+/// Read from `executable_path`. This is the path the process was launched with, which
+/// may be relative or go through symlinks.
+///
+/// With the `sysctl-fallback` feature, the path is asked from the kernel with
+/// `proc_pidpath` when `executable_path` isn't present. The apple argument always takes
+/// precedence, so the fallback costs nothing when it's there. The kernel's answer is
+/// always absolute and is only asked for once.
+#[inline]
+pub fn executable_path() -> Option<&'static Path> {
+    let value = apple_getenv(b"executable_path");
+
+    #[cfg(feature = "sysctl-fallback")]
+    return path_or_else(value, kernel_executable_path);
+
+    #[cfg(not(feature = "sysctl-fallback"))]
+    value.map(parse_path)
+}
+
+#[cfg(feature = "sysctl-fallback")]
+fn path_or_else(
+    value: Option<&'static [u8]>,
+    fallback: impl FnOnce() -> Option<&'static Path>,
+) -> Option<&'static Path> {
+    match value {
+        Some(value) => Some(parse_path(value)),
+        None => fallback(),
+    }
+}
+
+#[cfg(feature = "sysctl-fallback")]
+fn kernel_executable_path() -> Option<&'static Path> {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+    use std::sync::OnceLock;
+
+    extern "C" {
+        fn proc_pidpath(pid: c_int, buffer: *mut c_void, buffersize: u32) -> c_int;
+    }
+
+    /// `PROC_PIDPATHINFO_MAXSIZE` from `<sys/proc_info.h>`.
+    const MAX_SIZE: usize = 4 * 1024;
+
+    static PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    PATH.get_or_init(|| {
+        let pid = c_int::try_from(std::process::id()).ok()?;
+        let mut buf = vec![0u8; MAX_SIZE];
+        // Safety: `buf` is valid for writes of its whole length, which is what's passed.
+        let len = unsafe { proc_pidpath(pid, buf.as_mut_ptr().cast(), MAX_SIZE as u32) };
+
+        let len = usize::try_from(len).ok().filter(|&len| len > 0)?;
+        buf.truncate(len);
+        Some(PathBuf::from(OsString::from_vec(buf)))
+    })
+    .as_deref()
+}
+
+/// The file name of the main executable, which is the last component of
+/// [`executable_path`].
+///
+/// Returns `None` if `executable_path` isn't present or doesn't end in a file name,
+/// like `/` or a path ending in `..`.
+#[inline]
+pub fn executable_name() -> Option<&'static OsStr> {
+    executable_path().and_then(Path::file_name)
+}
+
+/// Like [`executable_path`], but copied into an owned [`PathBuf`].
+pub fn executable_path_buf() -> Option<PathBuf> {
+    executable_path().map(Path::to_path_buf)
+}
+
+/// Like [`executable_path`], but made absolute if it was relative.
+///
+/// A relative path is joined onto the *current* working directory, which only gives
+/// the right path if the working directory hasn't changed since launch. Nothing else is
+/// resolved, so the result may still contain symlinks or `..` components.
+///
+/// Returns `None` if `executable_path` isn't present, or if it's relative and the
+/// current working directory can't be read.
+pub fn executable_path_absolute() -> Option<PathBuf> {
+    let path = executable_path()?;
+    if path.is_absolute() {
+        return Some(path.to_path_buf());
+    }
+
+    std::env::current_dir()
+        .ok()
+        .map(|cwd| absolute_in(path, &cwd))
+}
+
+/// Like [`executable_path`], but with every symlink and relative component resolved.
+///
+/// This calls [`std::fs::canonicalize`], so it touches the filesystem and the
+/// executable must still exist. That gives a stable identity for the executable no
+/// matter which symlink it was launched through. A relative `executable_path` is
+/// resolved against the *current* working directory, like
+/// [`executable_path_absolute`].
+///
+/// Returns `None` if `executable_path` isn't present or couldn't be resolved.
+pub fn executable_realpath() -> Option<PathBuf> {
+    realpath_in(executable_path()?)
+}
+
+fn realpath_in(path: &Path) -> Option<PathBuf> {
+    path.canonicalize().ok()
+}
+
+fn absolute_in(path: &Path, cwd: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        cwd.join(path)
+    }
+}
+
+/// Checks whether [`executable_path`] still refers to the running executable.
+///
+/// This compares it against [`std::env::current_exe`] after resolving symlinks and
+/// relative components of both, so it touches the filesystem. A mismatch usually means
+/// the executable was moved or replaced, or a symlink it was launched through changed.
+///
+/// Returns `None` if `executable_path` is absent, or if either path couldn't be resolved,
+/// which includes a relative `executable_path` after the working directory changed.
+pub fn verify_executable_path() -> Option<bool> {
+    let current = std::env::current_exe().ok()?;
+    same_file(executable_path()?, &current)
+}
+
+fn same_file(a: &Path, b: &Path) -> Option<bool> {
+    Some(realpath_in(a)? == realpath_in(b)?)
+}
+
+/// The mach port name of the process' main thread.
+///
+/// Read from `th_port`, which libpthread takes ownership of and erases early on. This
+/// accepts both the `0x` prefixed hex the kernel uses, and decimal.
+#[inline]
+pub fn th_port() -> Option<u32> {
+    apple_getenv(b"th_port").and_then(parse_port)
+}
+
+/// The cookie used by libplatform to mangle pointers stored in places like `jmp_buf`s.
+///
+/// Read from `ptr_munge`. libplatform erases this after reading it, so expect it to
+/// be absent.
+#[inline]
+pub fn ptr_munge() -> Option<u64> {
+    apple_getenv(b"ptr_munge").and_then(parse_hex)
+}
+
+/// Recovers a pointer mangled by libplatform, such as one saved in a `jmp_buf`, using
+/// the [`ptr_munge`] cookie.
+///
+/// libplatform's `_OS_PTR_MUNGE` mangles a pointer by XORing it with the cookie, with no
+/// rotation unlike glibc's scheme, so demangling is the same XOR. Returns `None` if
+/// `ptr_munge` is absent, which is the usual case as libplatform erases it, or if the
+/// cookie doesn't fit in a pointer.
+#[inline]
+pub fn demangle_pointer(mangled: usize) -> Option<usize> {
+    Some(demangle_with(mangled, narrow(ptr_munge()?)?))
+}
+
+fn demangle_with(mangled: usize, cookie: usize) -> usize {
+    mangled ^ cookie
+}
+
+/// The layout of the main thread's stack, when the executable asked for a custom one.
+///
+/// Read from `main_stack`. libpthread erases this when it sets up the main thread, so
+/// expect it to be absent.
+#[inline]
+pub fn main_stack() -> Option<MainStack> {
+    apple_getenv(b"main_stack").and_then(MainStack::parse)
+}
+
+/// Whether the main thread's custom stack has a guard region below it.
+///
+/// This is [`MainStack::guard_size`] being non-zero, read from `main_stack` like
+/// [`main_stack`], so it's usually absent.
+#[inline]
+pub fn main_stack_guard_present() -> Option<bool> {
+    main_stack().map(|stack| stack.guard_size() > 0)
+}
+
+/// The main thread's stack layout, as returned by [`main_stack`].
+///
+/// The values are always parsed as `u64`s, whatever the target's pointer width. The
+/// `_usize` accessors convert them for use as addresses and sizes, returning `None`
+/// instead of truncating a value too large for the target, such as on the 32-bit
+/// `arm64_32` watchOS targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MainStack {
+    /// The address of the top of the stack, where it starts growing down from.
+    pub stack_top: u64,
+    /// The usable size of the stack.
+    pub stack_size: u64,
+    /// The address of the whole allocation containing the stack and its guard.
+    pub alloc_base: u64,
+    /// The size of the whole allocation containing the stack and its guard.
+    pub alloc_size: u64,
+}
+
+impl MainStack {
+    /// The size of the guard region below the stack, which is whatever part of the
+    /// allocation isn't usable stack.
+    #[inline]
+    pub fn guard_size(&self) -> u64 {
+        self.alloc_size.saturating_sub(self.stack_size)
+    }
+
+    /// [`stack_top`](Self::stack_top) as a `usize`, or `None` if it doesn't fit in one.
+    #[inline]
+    pub fn stack_top_usize(&self) -> Option<usize> {
+        narrow(self.stack_top)
+    }
+
+    /// [`stack_size`](Self::stack_size) as a `usize`, or `None` if it doesn't fit in one.
+    #[inline]
+    pub fn stack_size_usize(&self) -> Option<usize> {
+        narrow(self.stack_size)
+    }
+
+    /// [`alloc_base`](Self::alloc_base) as a `usize`, or `None` if it doesn't fit in one.
+    #[inline]
+    pub fn alloc_base_usize(&self) -> Option<usize> {
+        narrow(self.alloc_base)
+    }
+
+    /// [`alloc_size`](Self::alloc_size) as a `usize`, or `None` if it doesn't fit in one.
+    #[inline]
+    pub fn alloc_size_usize(&self) -> Option<usize> {
+        narrow(self.alloc_size)
+    }
+
+    fn parse(value: &[u8]) -> Option<Self> {
+        let [stack_top, stack_size, alloc_base, alloc_size] = parse_hex_list(value)?;
+
+        Some(Self {
+            stack_top,
+            stack_size,
+            alloc_base,
+            alloc_size,
+        })
+    }
+}
+
+/// Whether pointer authentication has been disabled for the process.
+///
+/// Read from `ptrauth_disabled`.
+#[inline]
+pub fn ptrauth_disabled() -> Option<bool> {
+    ptrauth_disabled_in(apple_getenv)
+}
+
+fn ptrauth_disabled_in<'a>(get: impl Fn(&[u8]) -> Option<&'a [u8]>) -> Option<bool> {
+    get(b"ptrauth_disabled").and_then(parse_bool)
+}
+
+/// Whether the process was forced to use 4K pages on a 16K page system.
+///
+/// Read from `vm_force_4k_pages`.
+#[inline]
+pub fn vm_force_4k_pages() -> Option<bool> {
+    vm_force_4k_pages_in(apple_getenv)
+}
+
+fn vm_force_4k_pages_in<'a>(get: impl Fn(&[u8]) -> Option<&'a [u8]>) -> Option<bool> {
+    get(b"vm_force_4k_pages").and_then(parse_bool)
+}
+
+/// Which arm64e ABI the process is allowed to use.
+///
+/// Read from `arm64e_abi`.
+#[inline]
+pub fn arm64e_abi() -> Option<Arm64eAbi> {
+    arm64e_abi_in(apple_getenv)
+}
+
+fn arm64e_abi_in<'a>(get: impl Fn(&[u8]) -> Option<&'a [u8]>) -> Option<Arm64eAbi> {
+    get(b"arm64e_abi").and_then(Arm64eAbi::parse)
+}
+
+/// The UUID of the dyld shared cache the process was launched with.
+///
+/// Read from `dyld_shared_cache_uuid`, accepting either the hyphenated UUID form or
+/// 32 bare hex digits. XNU doesn't pass this itself, so it's only present when the
+/// launching environment provides it.
+#[inline]
+pub fn dyld_cache_uuid() -> Option<[u8; 16]> {
+    apple_getenv(b"dyld_shared_cache_uuid").and_then(parse_uuid)
+}
+
+/// The code directory hash of the main executable's code signature.
+///
+/// Read from `executable_cdhash`, which the kernel formats as 40 lowercase hex
+/// digits. This is the leading 20 bytes of the hash, like `codesign` reports.
+#[inline]
+pub fn executable_cdhash() -> Option<[u8; 20]> {
+    apple_getenv(b"executable_cdhash").and_then(parse_hex_bytes)
+}
+
+/// Another hash the kernel passes alongside [`executable_cdhash`].
+///
+/// Read from `executable_boothash`, which the kernel formats the same way, as 40
+/// lowercase hex digits. What exactly it hashes isn't documented.
+///
+/// This is the only boot related argument that's been seen in practice, so there are
+/// no accessors for things like a boot session UUID.
+#[inline]
+pub fn executable_boothash() -> Option<[u8; 20]> {
+    apple_getenv(b"executable_boothash").and_then(parse_hex_bytes)
+}
+
+/// The stack protector canary the kernel generated for the process.
+///
+/// Read from the first value of `stack_guard`. libSystem erases this argument as soon
+/// as it has set up the canary, so it's almost never still present by the time this
+/// crate captures the arguments.
+#[inline]
+pub fn stack_guard() -> Option<u64> {
+    apple_getenv(b"stack_guard").and_then(parse_first_hex)
+}
+
+/// Says whether `stack_guard` is present without revealing it, for logging.
+///
+/// Returns `"<redacted>"` if the argument is present, even if it's malformed, and
+/// `"<absent>"` otherwise. See [`AppleEnvOwned::without_secrets`] for dropping it and
+/// the other secrets from a whole snapshot.
+///
+/// [`AppleEnvOwned::without_secrets`]: crate::env::AppleEnvOwned::without_secrets
+#[inline]
+pub fn stack_guard_redacted() -> &'static str {
+    redacted(apple_getenv(b"stack_guard"))
+}
+
+fn redacted(value: Option<&[u8]>) -> &'static str {
+    match value {
+        Some(_) => "<redacted>",
+        None => "<absent>",
+    }
+}
+
+/// Checks the `stack_guard` apple argument against the canary actually in use by
+/// the process, as a diagnostic for tampering or mismatched initialization.
+///
+/// Returns `None` if the argument is absent or malformed, which is the common case
+/// as explained in [`stack_guard`].
+pub fn verify_stack_guard() -> Option<bool> {
+    extern "C" {
+        /// libSystem's stack protector canary, which is an array of `long`s whose
+        /// first element is the one used by compiled code.
+        static __stack_chk_guard: std::os::raw::c_long;
+    }
+
+    let expected = stack_guard()?;
+    // Safety: The canary is written once during libSystem's initialization,
+    // which finishes before any of our code can run, so nothing can be writing
+    // to it concurrently. A `c_long` is always valid to read.
+    let live = unsafe { core::ptr::addr_of!(__stack_chk_guard).read() };
+
+    // Reinterpreted as unsigned first, so a 32-bit canary with its top bit set
+    // isn't sign extended. Only the second cast is a no-op on 64-bit targets.
+    #[allow(clippy::unnecessary_cast)]
+    let live = live as std::os::raw::c_ulong as u64;
+
+    Some(expected == live)
+}
+
+/// Whether the process is running under Rosetta 2 translation.
+///
+/// The kernel doesn't pass an apple argument saying so, so unlike the rest of this
+/// module, this asks the `sysctl.proc_translated` sysctl instead. Systems without
+/// Rosetta don't have that sysctl, which is reported as `false`, the same as for a
+/// native process.
+///
+/// Returns `None` if the sysctl failed for any other reason.
+pub fn is_translated() -> Option<bool> {
+    extern "C" {
+        fn sysctlbyname(
+            name: *const c_char,
+            oldp: *mut c_void,
+            oldlenp: *mut usize,
+            newp: *mut c_void,
+            newlen: usize,
+        ) -> c_int;
+    }
+
+    let mut value: c_int = 0;
+    let mut len = core::mem::size_of::<c_int>();
+    // Safety: The name is nul-terminated, and `value` and `len` describe a buffer
+    // the size of the `int` this sysctl returns.
+    let ret = unsafe {
+        sysctlbyname(
+            c"sysctl.proc_translated".as_ptr(),
+            core::ptr::addr_of_mut!(value).cast(),
+            &mut len,
+            core::ptr::null_mut(),
+            0,
+        )
+    };
+
+    let result = match ret {
+        0 => Ok(value),
+        _ => Err(std::io::Error::last_os_error().kind()),
+    };
+    translated_from(result)
+}
+
+fn translated_from(result: Result<c_int, std::io::ErrorKind>) -> Option<bool> {
+    match result {
+        Ok(value) => Some(value == 1),
+        // `ENOENT`, when the sysctl doesn't exist.
+        Err(std::io::ErrorKind::NotFound) => Some(false),
+        Err(_) => None,
+    }
+}
+
+/// The allocator tuning the kernel passed to libmalloc.
+///
+/// See [`MallocTuning`] for which arguments this reads.
+#[inline]
+pub fn malloc_tuning() -> MallocTuning {
+    MallocTuning::read(apple_getenv)
+}
+
+/// Allocator related apple arguments, gathered by [`malloc_tuning`].
+///
+/// Fields are `None` when their argument is absent or malformed.
+///
+/// There's no field for a `nano_malloc` argument, because XNU never passes one. The
+/// nano allocator is controlled by `MallocNanoZone` alone, which is [`nano_zone`] here
+/// and [`malloc_nano_zone_enabled`] on its own.
+///
+/// [`nano_zone`]: Self::nano_zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct MallocTuning {
+    /// Random values seeding the heap's randomization, from `malloc_entropy`.
+    ///
+    /// libmalloc usually erases this after reading it, so expect it to be absent.
+    pub entropy: Option<[u64; 2]>,
+    /// Whether the nano allocator should be engaged, from `MallocNanoZone`.
+    pub nano_zone: Option<bool>,
+}
+
+impl MallocTuning {
+    fn read<'a>(get: impl Fn(&[u8]) -> Option<&'a [u8]>) -> Self {
+        Self {
+            entropy: get(b"malloc_entropy").and_then(parse_hex_list),
+            nano_zone: get(b"MallocNanoZone").and_then(parse_bool),
+        }
+    }
+}
+
+/// Whether libmalloc should engage its nano allocator for small allocations.
+///
+/// Read from `MallocNanoZone`, which the kernel sets to `1` or `0` depending on the
+/// platform and the executable. This is the same as [`MallocTuning::nano_zone`].
+#[inline]
+pub fn malloc_nano_zone_enabled() -> Option<bool> {
+    apple_getenv(b"MallocNanoZone").and_then(parse_bool)
+}
+
+/// The runtime hardening configuration the kernel passed, for summarizing in
+/// diagnostics.
+///
+/// See [`Hardening`] for which arguments this reads. No sanitizer passes its options
+/// through apple arguments, they all use the real environment, so those aren't covered.
+#[inline]
+pub fn hardening() -> Hardening {
+    Hardening::read(apple_getenv)
+}
+
+/// Hardening related apple arguments, gathered by [`hardening`].
+///
+/// Fields are `None` when their argument is absent or malformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct Hardening {
+    /// Whether pointer authentication is disabled, from `ptrauth_disabled`.
+    pub ptrauth_disabled: Option<bool>,
+    /// Which arm64e ABI is allowed, from `arm64e_abi`.
+    pub arm64e_abi: Option<Arm64eAbi>,
+    /// Whether the nano allocator is engaged, from `MallocNanoZone`.
+    pub malloc_nano_zone: Option<bool>,
+}
+
+impl Hardening {
+    fn read<'a>(get: impl Fn(&[u8]) -> Option<&'a [u8]>) -> Self {
+        Self {
+            ptrauth_disabled: ptrauth_disabled_in(&get),
+            arm64e_abi: arm64e_abi_in(&get),
+            malloc_nano_zone: get(b"MallocNanoZone").and_then(parse_bool),
+        }
+    }
+}
+
+/// The address of the main executable's Mach-O header.
+///
+/// Read from `main_executable_mh` as a `0x` prefixed hex address. XNU doesn't pass this
+/// itself, so it's only present when the launching environment provides it. Prefer
+/// `_dyld_get_image_header(0)` when it isn't.
+///
+/// This returns the address as a `usize`, like [`MainStack`], so that [`KnownArgs`] can
+/// be shared between threads. Cast it to a pointer to use it, but nothing is known about
+/// what it points to, so dereferencing it is entirely up to the caller.
+#[inline]
+pub fn main_executable_mh() -> Option<usize> {
+    apple_getenv(b"main_executable_mh").and_then(parse_address)
+}
+
+/// The file dyld, the dynamic linker, was loaded from.
+///
+/// Read from `dyld_file`. Despite the name, this isn't a path: the kernel identifies the
+/// file by the filesystem and inode it was loaded from, formatted as two comma separated
+/// hex numbers. The raw value is still available from
+/// [`apple_var_os`](crate::env::apple_var_os).
+#[inline]
+pub fn dyld_file() -> Option<FileId> {
+    apple_getenv(b"dyld_file").and_then(FileId::parse)
+}
+
+/// A file identified by where it's stored rather than by its path, as returned by
+/// [`dyld_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId {
+    /// The ID of the filesystem the file is on, packed from its `fsid_t`.
+    pub fsid: u64,
+    /// The inode number of the file, like `st_ino` from `stat`.
+    pub inode: u64,
+}
+
+impl FileId {
+    fn parse(value: &[u8]) -> Option<Self> {
+        let [fsid, inode] = parse_hex_list(value)?;
+        Some(Self { fsid, inode })
+    }
+}
+
+/// The name of the XPC service or `launchd` job the process was started as.
+///
+/// Read from `XPC_SERVICE_NAME`, as a reverse-DNS name like `com.example.helper`.
+/// `launchd` normally sets this in the real environment instead, so this is only present
+/// when the launching environment passes it as an apple argument too.
+#[inline]
+pub fn xpc_service_name() -> Option<&'static str> {
+    apple_getenv(b"XPC_SERVICE_NAME").and_then(parse_str)
+}
+
+/// The flags `launchd` started the process' XPC service with.
+///
+/// Read from `XPC_FLAGS`, as a `0x` prefixed hex number. Like [`xpc_service_name`], this
+/// is usually only in the real environment. The meaning of the bits isn't public.
+#[inline]
+pub fn xpc_flags() -> Option<u64> {
+    apple_getenv(b"XPC_FLAGS").and_then(parse_hex)
+}
+
+/// Returns every XPC related `key=value` apple argument, in order.
+///
+/// These are the arguments whose keys start with `xpc_`, `XPC_`, or `__XPC_`, including
+/// ones which don't have a typed accessor here. Their values are passed through unparsed,
+/// and are also available through [`apple_var`](crate::env::apple_var).
+pub fn xpc_vars() -> impl DoubleEndedIterator<Item = (&'static OsStr, &'static OsStr)> {
+    xpc_vars_in(crate::sys::args_slice())
+}
+
+fn xpc_vars_in<T: AsRef<[u8]>>(
+    args: &'static [T],
+) -> impl DoubleEndedIterator<Item = (&'static OsStr, &'static OsStr)> {
+    const PREFIXES: [&[u8]; 3] = [b"xpc_", b"XPC_", b"__XPC_"];
+
+    args.iter()
+        .filter_map(|arg| split_kv(arg.as_ref()))
+        .filter(|(k, _)| PREFIXES.iter().any(|p| k.starts_with(p)))
+        .map(|(k, v)| (OsStr::from_bytes(k), OsStr::from_bytes(v)))
+}
+
+/// Whether the process seems to be running in a simulator, like the iOS Simulator.
+///
+/// The kernel doesn't say so directly, so this is a heuristic based on where the
+/// executable was launched from, read from `executable_path`:
+/// - Paths inside a `Library/Developer/CoreSimulator/` directory, where simulator
+///   devices keep their installed apps, mean it is.
+/// - Paths inside `/private/var/containers/Bundle/` (or `/var`, its symlink), where
+///   devices install apps, mean it isn't.
+/// - Anything else, including a missing path, is inconclusive and returns `None`.
+///   This includes every macOS executable, and anything launched from outside an app
+///   bundle, such as test binaries run with `simctl spawn`.
+///
+/// Setting the `APPLEARGS_SIMULATOR` environment variable to `1` or `0` overrides
+/// this, for harnesses which know better.
+pub fn is_simulator() -> Option<bool> {
+    let forced = std::env::var_os("APPLEARGS_SIMULATOR");
+    detect_simulator(forced.as_deref(), apple_getenv(b"executable_path"))
+}
+
+fn detect_simulator(forced: Option<&OsStr>, executable_path: Option<&[u8]>) -> Option<bool> {
+    const SIMULATOR_DIR: &[u8] = b"/Library/Developer/CoreSimulator/";
+    const DEVICE_DIRS: [&[u8]; 2] = [
+        b"/private/var/containers/Bundle/",
+        b"/var/containers/Bundle/",
+    ];
+
+    if let Some(forced) = forced {
+        return parse_bool(forced.as_bytes());
+    }
+
+    let path = executable_path?;
+    if path
+        .windows(SIMULATOR_DIR.len())
+        .any(|w| w == SIMULATOR_DIR)
+    {
+        Some(true)
+    } else if DEVICE_DIRS.iter().any(|dir| path.starts_with(dir)) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Returns every apple argument this module understands, parsed in a single pass.
+///
+/// Anything not recognized ends up in [`KnownArgs::other`].
+pub fn dump() -> KnownArgs {
+    let known = KnownArgs::from_args(crate::sys::args_slice());
+
+    #[cfg(feature = "sysctl-fallback")]
+    let known = KnownArgs {
+        executable_path: known.executable_path.or_else(kernel_executable_path),
+        ..known
+    };
+
+    known
+}
+
+/// A parsed view of all the apple arguments, as returned by [`dump`].
+///
+/// Each field is `None` when its argument is absent or malformed, and holds the same
+/// value as the accessor of the same name in this module, including the
+/// `sysctl-fallback` feature's fallback for `executable_path`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct KnownArgs {
+    /// See [`executable_path`].
+    pub executable_path: Option<&'static Path>,
+    /// See [`executable_cdhash`].
+    pub executable_cdhash: Option<[u8; 20]>,
+    /// See [`executable_boothash`].
+    pub executable_boothash: Option<[u8; 20]>,
+    /// See [`main_executable_mh`].
+    pub main_executable_mh: Option<usize>,
+    /// See [`th_port`].
+    pub th_port: Option<u32>,
+    /// See [`main_stack`].
+    pub main_stack: Option<MainStack>,
+    /// See [`stack_guard`].
+    pub stack_guard: Option<u64>,
+    /// See [`ptr_munge`].
+    pub ptr_munge: Option<u64>,
+    /// See [`malloc_tuning`].
+    pub malloc: MallocTuning,
+    /// See [`ptrauth_disabled`].
+    pub ptrauth_disabled: Option<bool>,
+    /// See [`arm64e_abi`].
+    pub arm64e_abi: Option<Arm64eAbi>,
+    /// See [`vm_force_4k_pages`].
+    pub vm_force_4k_pages: Option<bool>,
+    /// See [`dyld_cache_uuid`].
+    pub dyld_cache_uuid: Option<[u8; 16]>,
+    /// See [`dyld_file`].
+    pub dyld_file: Option<FileId>,
+    /// See [`xpc_service_name`].
+    pub xpc_service_name: Option<&'static str>,
+    /// See [`xpc_flags`].
+    pub xpc_flags: Option<u64>,
+    /// Every `key=value` argument that isn't recognized, in order.
+    pub other: Vec<(&'static OsStr, &'static OsStr)>,
+}
+
+impl KnownArgs {
+    fn from_args<T: AsRef<[u8]>>(args: &'static [T]) -> Self {
+        let mut known = Self::default();
+
+        for (key, value) in args.iter().filter_map(|arg| split_kv(arg.as_ref())) {
+            match key {
+                b"executable_path" => known.executable_path = Some(parse_path(value)),
+                b"executable_cdhash" => known.executable_cdhash = parse_hex_bytes(value),
+                b"executable_boothash" => known.executable_boothash = parse_hex_bytes(value),
+                b"main_executable_mh" => known.main_executable_mh = parse_address(value),
+                b"th_port" => known.th_port = parse_port(value),
+                b"main_stack" => known.main_stack = MainStack::parse(value),
+                b"stack_guard" => known.stack_guard = parse_first_hex(value),
+                b"ptr_munge" => known.ptr_munge = parse_hex(value),
+                b"malloc_entropy" => known.malloc.entropy = parse_hex_list(value),
+                b"MallocNanoZone" => known.malloc.nano_zone = parse_bool(value),
+                b"ptrauth_disabled" => known.ptrauth_disabled = parse_bool(value),
+                b"arm64e_abi" => known.arm64e_abi = Arm64eAbi::parse(value),
+                b"vm_force_4k_pages" => known.vm_force_4k_pages = parse_bool(value),
+                b"dyld_shared_cache_uuid" => known.dyld_cache_uuid = parse_uuid(value),
+                b"dyld_file" => known.dyld_file = FileId::parse(value),
+                b"XPC_SERVICE_NAME" => known.xpc_service_name = parse_str(value),
+                b"XPC_FLAGS" => known.xpc_flags = parse_hex(value),
+                _ => known
+                    .other
+                    .push((OsStr::from_bytes(key), OsStr::from_bytes(value))),
+            }
+        }
+
+        known
+    }
+}
+
+/// Returns each apple argument this module understands which is present, in the order
+/// they first appear.
+///
+/// Every key is only yielded once, even if it's repeated. Unrecognized keys are
+/// skipped, see [`apple_vars_os`](crate::env::apple_vars_os) for those.
+pub fn present() -> impl Iterator<Item = AppleArg> {
+    present_in(crate::sys::args_slice())
+}
+
+fn present_in<T: AsRef<[u8]>>(args: &[T]) -> impl Iterator<Item = AppleArg> + '_ {
+    let mut seen = [false; AppleArg::ALL.len()];
+
+    args.iter()
+        .filter_map(|arg| split_kv(arg.as_ref()))
+        .filter_map(|(key, _)| AppleArg::from_key(key))
+        .filter(move |&arg| !core::mem::replace(&mut seen[arg as usize], true))
+}
+
+/// An apple argument this module understands, as returned by [`present`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum AppleArg {
+    /// `executable_path`, read by [`executable_path`].
+    ExecutablePath,
+    /// `executable_cdhash`, read by [`executable_cdhash`].
+    ExecutableCdhash,
+    /// `executable_boothash`, read by [`executable_boothash`].
+    ExecutableBoothash,
+    /// `main_executable_mh`, read by [`main_executable_mh`].
+    MainExecutableMh,
+    /// `th_port`, read by [`th_port`].
+    ThPort,
+    /// `main_stack`, read by [`main_stack`].
+    MainStack,
+    /// `stack_guard`, read by [`stack_guard`].
+    StackGuard,
+    /// `ptr_munge`, read by [`ptr_munge`].
+    PtrMunge,
+    /// `malloc_entropy`, read by [`malloc_tuning`].
+    MallocEntropy,
+    /// `MallocNanoZone`, read by [`malloc_nano_zone_enabled`].
+    MallocNanoZone,
+    /// `ptrauth_disabled`, read by [`ptrauth_disabled`].
+    PtrauthDisabled,
+    /// `arm64e_abi`, read by [`arm64e_abi`].
+    Arm64eAbi,
+    /// `vm_force_4k_pages`, read by [`vm_force_4k_pages`].
+    VmForce4kPages,
+    /// `dyld_shared_cache_uuid`, read by [`dyld_cache_uuid`].
+    DyldSharedCacheUuid,
+    /// `dyld_file`, read by [`dyld_file`].
+    DyldFile,
+    /// `XPC_SERVICE_NAME`, read by [`xpc_service_name`].
+    XpcServiceName,
+    /// `XPC_FLAGS`, read by [`xpc_flags`].
+    XpcFlags,
+}
+
+/// Every apple argument this module understands, with its key and how [`get_typed`]
+/// parses it, in the order of [`AppleArg`]'s variants.
+///
+/// Adding a key means adding its variant and a row here, which everything else in
+/// [`AppleArg`] is derived from.
+#[rustfmt::skip]
+const TABLE: &[(&str, AppleArg, KnownKind)] = &[
+    ("executable_path", AppleArg::ExecutablePath, KnownKind::Path),
+    ("executable_cdhash", AppleArg::ExecutableCdhash, KnownKind::Raw),
+    ("executable_boothash", AppleArg::ExecutableBoothash, KnownKind::Raw),
+    ("main_executable_mh", AppleArg::MainExecutableMh, KnownKind::Hex),
+    ("th_port", AppleArg::ThPort, KnownKind::Port),
+    ("main_stack", AppleArg::MainStack, KnownKind::HexList),
+    ("stack_guard", AppleArg::StackGuard, KnownKind::FirstHex),
+    ("ptr_munge", AppleArg::PtrMunge, KnownKind::Hex),
+    ("malloc_entropy", AppleArg::MallocEntropy, KnownKind::HexList),
+    ("MallocNanoZone", AppleArg::MallocNanoZone, KnownKind::Bool),
+    ("ptrauth_disabled", AppleArg::PtrauthDisabled, KnownKind::Bool),
+    ("arm64e_abi", AppleArg::Arm64eAbi, KnownKind::Raw),
+    ("vm_force_4k_pages", AppleArg::VmForce4kPages, KnownKind::Bool),
+    ("dyld_shared_cache_uuid", AppleArg::DyldSharedCacheUuid, KnownKind::Raw),
+    ("dyld_file", AppleArg::DyldFile, KnownKind::HexList),
+    ("XPC_SERVICE_NAME", AppleArg::XpcServiceName, KnownKind::Raw),
+    ("XPC_FLAGS", AppleArg::XpcFlags, KnownKind::Hex),
+];
+
+// `AppleArg::key` and `AppleArg::kind` index `TABLE` by the variant.
+const _: () = {
+    let mut i = 0;
+    while i < TABLE.len() {
+        assert!(TABLE[i].1 as usize == i, "`TABLE` is out of order");
+        i += 1;
+    }
+};
+
+impl AppleArg {
+    /// Every apple argument this module understands.
+    pub const ALL: &'static [Self] = &{
+        let mut all = [Self::ExecutablePath; TABLE.len()];
+        let mut i = 0;
+        while i < TABLE.len() {
+            all[i] = TABLE[i].1;
+            i += 1;
+        }
+        all
+    };
+
+    /// Returns the key this argument is passed under.
+    pub fn key(self) -> &'static str {
+        TABLE[self as usize].0
+    }
+
+    /// Returns how [`get_typed`] parses this argument.
+    pub fn kind(self) -> KnownKind {
+        TABLE[self as usize].2
+    }
+
+    /// Returns the argument passed under `key`, if it's one this module understands.
+    pub fn from_key(key: &[u8]) -> Option<Self> {
+        TABLE
+            .iter()
+            .find(|(k, ..)| k.as_bytes() == key)
+            .map(|&(_, arg, _)| arg)
+    }
+}
+
+/// How [`get_typed`] parses an apple argument's value, as returned by
+/// [`AppleArg::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum KnownKind {
+    /// A `0x` prefixed hex number.
+    Hex,
+    /// The first of comma separated `0x` prefixed hex numbers, ignoring the rest.
+    FirstHex,
+    /// Comma separated `0x` prefixed hex numbers.
+    HexList,
+    /// A filesystem path.
+    Path,
+    /// A mach port name, in hex or decimal.
+    Port,
+    /// `1` or `0`.
+    Bool,
+    /// Anything else, which is left as is. This includes hashes and UUIDs, which have
+    /// their own accessors.
+    Raw,
+}
+
+/// The parsed value of an apple argument, as returned by [`get_typed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KnownValue {
+    /// A value of [`KnownKind::Hex`] or [`KnownKind::FirstHex`].
+    Hex(u64),
+    /// A value of [`KnownKind::HexList`].
+    HexList(Vec<u64>),
+    /// A value of [`KnownKind::Path`].
+    Path(&'static Path),
+    /// A value of [`KnownKind::Port`].
+    Port(u32),
+    /// A value of [`KnownKind::Bool`].
+    Bool(bool),
+    /// A value of [`KnownKind::Raw`].
+    Raw(&'static OsStr),
+}
+
+/// Returns the value of the apple argument `key`, parsed according to its
+/// [`KnownKind`].
+///
+/// This is a generic version of the other accessors in this module, for code that
+/// handles many arguments the same way. Returns `None` if `key` isn't one this module
+/// understands, or if it's absent or malformed.
+pub fn get_typed(key: impl VarKey) -> Option<KnownValue> {
+    let key = key.key_bytes();
+    typed(AppleArg::from_key(key)?, apple_getenv(key)?)
+}
+
+fn typed(arg: AppleArg, value: &'static [u8]) -> Option<KnownValue> {
+    Some(match arg.kind() {
+        KnownKind::Hex => KnownValue::Hex(parse_hex(value)?),
+        KnownKind::FirstHex => KnownValue::Hex(parse_first_hex(value)?),
+        KnownKind::HexList => KnownValue::HexList(
+            split_list(value, b',')
+                .map(parse_hex)
+                .collect::<Option<_>>()?,
+        ),
+        KnownKind::Path => KnownValue::Path(parse_path(value)),
+        KnownKind::Port => KnownValue::Port(parse_port(value)?),
+        KnownKind::Bool => KnownValue::Bool(parse_bool(value)?),
+        KnownKind::Raw => KnownValue::Raw(OsStr::from_bytes(value)),
+    })
+}
+
+/// The arm64e ABI variant the kernel reports for this process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Arm64eAbi {
+    /// Only the OS-internal arm64e ABI is supported.
+    Os,
+    /// Any arm64e binary is allowed to run.
+    All,
+}
+
+impl Arm64eAbi {
+    fn parse(value: &[u8]) -> Option<Self> {
+        match value {
+            b"os" => Some(Self::Os),
+            b"all" => Some(Self::All),
+            _ => None,
+        }
+    }
+}
+
+fn parse_path(value: &[u8]) -> &Path {
+    Path::new(OsStr::from_bytes(value))
+}
+
+fn parse_str(value: &[u8]) -> Option<&str> {
+    core::str::from_utf8(value).ok()
+}
+
+/// Parses a mach port name, in either hex or decimal.
+fn parse_port(value: &[u8]) -> Option<u32> {
+    match value.strip_prefix(b"0x") {
+        Some(_) => u32::try_from(parse_hex(value)?).ok(),
+        None => core::str::from_utf8(value).ok()?.parse().ok(),
+    }
+}
+
+fn parse_bool(value: &[u8]) -> Option<bool> {
+    match value {
+        b"0" => Some(false),
+        b"1" => Some(true),
+        _ => None,
+    }
+}
+
+/// Parses a `0x` prefixed hex number, as the kernel formats them.
+fn parse_hex(value: &[u8]) -> Option<u64> {
+    parse_hex_digits(value.strip_prefix(b"0x")?)
+}
+
+/// Converts `value` to a narrower integer, like a pointer sized one, if it fits.
+fn narrow<T: TryFrom<u64>>(value: u64) -> Option<T> {
+    T::try_from(value).ok()
+}
+
+/// Parses a hex address, rejecting ones which don't fit in a pointer.
+fn parse_address(value: &[u8]) -> Option<usize> {
+    narrow(parse_hex(value)?)
+}
+
+/// Parses the first of some comma separated hex numbers.
+fn parse_first_hex(value: &[u8]) -> Option<u64> {
+    split_list(value, b',').next().and_then(parse_hex)
+}
+
+/// Parses exactly `N` comma separated hex numbers.
+fn parse_hex_list<const N: usize>(value: &[u8]) -> Option<[u64; N]> {
+    let mut out = [0; N];
+    let mut fields = split_list(value, b',');
+
+    for slot in &mut out {
+        *slot = parse_hex(fields.next()?)?;
+    }
+
+    match fields.next() {
+        None => Some(out),
+        Some(_) => None,
+    }
+}
+
+/// Parses exactly `N` bytes from `2 * N` bare hex digits.
+fn parse_hex_bytes<const N: usize>(value: &[u8]) -> Option<[u8; N]> {
+    let mut out = [0; N];
+    decode_hex_into(value, &mut out)?;
+    Some(out)
+}
+
+fn parse_uuid(value: &[u8]) -> Option<[u8; 16]> {
+    const HYPHENS: [usize; 4] = [8, 13, 18, 23];
+
+    let hyphenated = match value.len() {
+        32 => false,
+        36 => true,
+        _ => return None,
+    };
+
+    let mut uuid = [0; 16];
+    let mut digits = value
+        .iter()
+        .enumerate()
+        .filter(|&(i, b)| !(hyphenated && HYPHENS.contains(&i) && *b == b'-'))
+        .map(|(_, &b)| hex_digit(b));
+
+    for byte in &mut uuid {
+        let hi = digits.next()??;
+        let lo = digits.next()??;
+        *byte = (hi << 4) | lo;
+    }
+
+    // A hyphenated value with a misplaced hyphen leaves digits behind.
+    match digits.next() {
+        None => Some(uuid),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::getenv_in;
+
+    #[test]
+    fn bool_parsing() {
+        assert_eq!(parse_bool(b"0"), Some(false));
+        assert_eq!(parse_bool(b"1"), Some(true));
+        assert_eq!(parse_bool(b""), None);
+        assert_eq!(parse_bool(b"2"), None);
+        assert_eq!(parse_bool(b"true"), None);
+        assert_eq!(parse_bool(b"10"), None);
+    }
+
+    #[test]
+    fn cdhash_parsing() {
+        const CDHASH: &str = "acd984a2fa40d1b36ba71094e7c0318a6bf15084";
+
+        let args: &[&[u8]] = &[b"executable_cdhash=acd984a2fa40d1b36ba71094e7c0318a6bf15084"];
+        let value = getenv_in(args, b"executable_cdhash").unwrap();
+        let hash: [u8; 20] = parse_hex_bytes(value).unwrap();
+
+        let round_trip: String = hash.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(round_trip, CDHASH);
+
+        assert_eq!(parse_hex_bytes::<20>(&value[1..]), None);
+        assert_eq!(
+            parse_hex_bytes::<20>(b"zcd984a2fa40d1b36ba71094e7c0318a6bf15084"),
+            None
+        );
+        assert_eq!(parse_hex_bytes::<1>(b"AB"), Some([0xab]));
+    }
+
+    #[test]
+    fn simulator_detection() {
+        let simulator: &[&[u8]] = &[
+            b"executable_path=/Users/person/Library/Developer/CoreSimulator/Devices/\
+              8A7F3C4B-2E51-4B0D-9A3E-6C1D2F7B9E04/data/Containers/Bundle/Application/\
+              1B6E0C9D-7F2A-4C83-B5D1-3E8A9F0C2D47/Demo.app/Demo",
+            b"executable_cdhash=acd984a2fa40d1b36ba71094e7c0318a6bf15084",
+        ];
+        let device: &[&[u8]] = &[
+            b"executable_path=/private/var/containers/Bundle/Application/\
+              1B6E0C9D-7F2A-4C83-B5D1-3E8A9F0C2D47/Demo.app/Demo",
+            b"arm64e_abi=os",
+        ];
+        let mac: &[&[u8]] = &[b"executable_path=/Users/person/dev/project/target/debug/bin"];
+
+        let detect = |args, forced: Option<&str>| {
+            detect_simulator(forced.map(OsStr::new), getenv_in(args, b"executable_path"))
+        };
+        assert_eq!(detect(simulator, None), Some(true));
+        assert_eq!(detect(device, None), Some(false));
+        assert_eq!(detect(mac, None), None);
+        assert_eq!(detect(&[], None), None);
+
+        assert_eq!(detect(simulator, Some("0")), Some(false));
+        assert_eq!(detect(mac, Some("1")), Some(true));
+        assert_eq!(detect(device, Some("maybe")), None);
+    }
+
+    #[test]
+    fn uuid_parsing() {
+        const UUID: [u8; 16] = [
+            0x4c, 0x4c, 0x44, 0x55, 0x55, 0x55, 0x35, 0x55, 0xa1, 0x96, 0x0b, 0x5f, 0x2e, 0x10,
+            0xfd, 0x3a,
+        ];
+
+        let args: &[&[u8]] = &[b"dyld_shared_cache_uuid=4C4C4455-5555-3555-A196-0B5F2E10FD3A"];
+        let value = getenv_in(args, b"dyld_shared_cache_uuid").unwrap();
+        assert_eq!(parse_uuid(value), Some(UUID));
+        assert_eq!(parse_uuid(b"4c4c445555553555a1960b5f2e10fd3a"), Some(UUID));
+
+        assert_eq!(parse_uuid(b"4C4C4455-5555-3555-A196-0B5F2E10FD3"), None);
+        assert_eq!(parse_uuid(b"4C4C44555-555-3555-A196-0B5F2E10FD3A"), None);
+        assert_eq!(parse_uuid(b"4C4C4455-5555-3555-A196-0B5F2E10FD3G"), None);
+        assert_eq!(parse_uuid(b"4c4c445555553555a1960b5f2e10fd3a00"), None);
+        assert_eq!(parse_uuid(b""), None);
+    }
+
+    #[test]
+    fn hex_parsing() {
+        assert_eq!(parse_hex(b"0x0"), Some(0));
+        assert_eq!(parse_hex(b"0xdeadBEEF"), Some(0xdead_beef));
+        assert_eq!(parse_hex(b"0xffffffffffffffff"), Some(u64::MAX));
+        assert_eq!(parse_hex(b"0x1ffffffffffffffff"), None);
+        assert_eq!(parse_hex(b"0x"), None);
+        assert_eq!(parse_hex(b"ff"), None);
+        assert_eq!(parse_hex(b"0xfg"), None);
+
+        assert_eq!(parse_first_hex(b"0x1,0x2"), Some(1));
+        assert_eq!(
+            parse_first_hex(b"0xa38b2f9c6e2d1400"),
+            Some(0xa38b2f9c6e2d1400)
+        );
+        assert_eq!(parse_first_hex(b",0x2"), None);
+
+        assert_eq!(parse_hex_list(b"0x1,0x2"), Some([1, 2]));
+        assert_eq!(parse_hex_list::<2>(b"0x1"), None);
+        assert_eq!(parse_hex_list::<2>(b"0x1,0x2,0x3"), None);
+        assert_eq!(parse_hex_list::<2>(b"0x1,"), None);
+    }
+
+    #[test]
+    fn address_parsing() {
+        let args: &[&[u8]] = &[b"main_executable_mh=0x100000000"];
+        let value = getenv_in(args, b"main_executable_mh").unwrap();
+
+        assert_eq!(parse_address(value), Some(0x1_0000_0000));
+        assert_eq!(parse_address(b"0x"), None);
+        assert_eq!(parse_address(b"100000000"), None);
+    }
+
+    #[test]
+    fn malloc_tuning_fields() {
+        let read = |args: &[&'static [u8]]| MallocTuning::read(|k| getenv_in(args, k));
+
+        assert_eq!(read(&[]), MallocTuning::default());
+
+        let entropy = read(&[b"malloc_entropy=0x5dc1c3a1fd3e1c2b,0x9f3e57e4c84b4d7a"]);
+        assert_eq!(
+            entropy.entropy,
+            Some([0x5dc1c3a1fd3e1c2b, 0x9f3e57e4c84b4d7a])
+        );
+        assert_eq!(entropy.nano_zone, None);
+
+        let nano = read(&[b"MallocNanoZone=0"]);
+        assert_eq!(nano.entropy, None);
+        assert_eq!(nano.nano_zone, Some(false));
+
+        let full = read(&[b"malloc_entropy=0x1,0x2", b"MallocNanoZone=1"]);
+        assert_eq!(
+            full,
+            MallocTuning {
+                entropy: Some([1, 2]),
+                nano_zone: Some(true),
+            }
+        );
+
+        assert_eq!(read(&[b"malloc_entropy=0x1"]).entropy, None);
+    }
+
+    #[test]
+    fn port_parsing() {
+        assert_eq!(parse_port(b"0x103"), Some(0x103));
+        assert_eq!(parse_port(b"259"), Some(259));
+        assert_eq!(parse_port(b"0x100000000"), None);
+        assert_eq!(parse_port(b"-1"), None);
+        assert_eq!(parse_port(b""), None);
+    }
+
+    #[test]
+    fn main_stack_parsing() {
+        let stack = MainStack::parse(b"0x16fdff000,0x80000,0x16fd7b000,0x84000").unwrap();
+
+        assert_eq!(stack.stack_top, 0x16fdff000);
+        assert_eq!(stack.stack_size, 0x80000);
+        assert_eq!(stack.alloc_base, 0x16fd7b000);
+        assert_eq!(stack.guard_size(), 0x4000);
+        assert_eq!(MainStack::parse(b"0x16fdff000,0x80000"), None);
+
+        let guard = |args: &[&[u8]]| {
+            getenv_in(args, b"main_stack")
+                .and_then(MainStack::parse)
+                .map(|stack| stack.guard_size() > 0)
+        };
+        assert_eq!(
+            guard(&[b"main_stack=0x16fdff000,0x80000,0x16fd7b000,0x84000"]),
+            Some(true)
+        );
+        assert_eq!(
+            guard(&[b"main_stack=0x16fdff000,0x80000,0x16fd7f000,0x80000"]),
+            Some(false)
+        );
+        assert_eq!(guard(&[b"main_stack="]), None);
+        assert_eq!(guard(&[]), None);
+    }
+
+    #[test]
+    fn redaction() {
+        assert_eq!(redacted(Some(b"0xa38b2f9c6e2d1400")), "<redacted>");
+        assert_eq!(redacted(Some(b"garbage")), "<redacted>");
+        assert_eq!(redacted(None), "<absent>");
+    }
+
+    #[test]
+    fn demangling() {
+        let cookie = 0x5f1d_3c8a;
+        let pointers = [0x1_0000_4000, 0x7ff7_bfef_f000, 0, usize::MAX];
+
+        for ptr in pointers {
+            let mangled = ptr ^ cookie;
+            assert_ne!(mangled, ptr);
+            assert_eq!(demangle_with(mangled, cookie), ptr);
+        }
+        assert_eq!(demangle_with(0x1234, 0), 0x1234);
+    }
+
+    #[test]
+    fn main_stack_widths() {
+        let stack = MainStack::parse(b"0x16fdff000,0x80000,0x16fd7b000,0x84000").unwrap();
+
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(stack.stack_top_usize(), Some(0x16fdff000));
+        assert_eq!(stack.stack_size_usize(), Some(0x80000));
+        assert_eq!(stack.alloc_size_usize(), Some(0x84000));
+
+        // The addresses are past `u32::MAX`, which a 32-bit target would reject.
+        assert_eq!(narrow::<u32>(stack.stack_top), None);
+        assert_eq!(narrow::<u32>(stack.alloc_base), None);
+        assert_eq!(narrow::<u32>(stack.stack_size), Some(0x80000));
+        assert_eq!(narrow::<u32>(u64::from(u32::MAX)), Some(u32::MAX));
+        assert_eq!(narrow::<u32>(u64::from(u32::MAX) + 1), None);
+    }
+
+    #[test]
+    fn snapshot_is_shareable() {
+        fn shareable<T: Send + Sync + 'static>() {}
+        shareable::<KnownArgs>();
+    }
+
+    #[test]
+    fn full_dump() {
+        let args: &'static [&'static [u8]] = &[
+            b"executable_path=/usr/bin/true",
+            b"executable_cdhash=acd984a2fa40d1b36ba71094e7c0318a6bf15084",
+            b"main_executable_mh=0x100000000",
+            b"th_port=0x103",
+            b"main_stack=0x16fdff000,0x80000,0x16fd7b000,0x84000",
+            b"stack_guard=0xa38b2f9c6e2d1400",
+            b"ptr_munge=0x5f1d3c8a",
+            b"malloc_entropy=0x1,0x2",
+            b"MallocNanoZone=1",
+            b"ptrauth_disabled=0",
+            b"arm64e_abi=all",
+            b"vm_force_4k_pages=1",
+            b"dyld_shared_cache_uuid=4c4c445555553555a1960b5f2e10fd3a",
+            b"executable_boothash=cd0228d404782f85c4ef3d65dc2ae92aaa66578b",
+            b"executable_file=0x1a0100000f,0x71b112",
+            b"token",
+        ];
+        let known = KnownArgs::from_args(args);
+
+        assert_eq!(known.executable_path, Some(Path::new("/usr/bin/true")));
+        assert_eq!(known.executable_cdhash.map(|h| h[0]), Some(0xac));
+        assert_eq!(
+            known.executable_boothash.map(|h| [h[0], h[19]]),
+            Some([0xcd, 0x8b])
+        );
+        assert_eq!(known.main_executable_mh, Some(0x1_0000_0000));
+        assert_eq!(known.th_port, Some(0x103));
+        assert_eq!(known.main_stack.map(|s| s.stack_size), Some(0x80000));
+        assert_eq!(known.stack_guard, Some(0xa38b2f9c6e2d1400));
+        assert_eq!(known.ptr_munge, Some(0x5f1d3c8a));
+        assert_eq!(
+            known.malloc,
+            MallocTuning {
+                entropy: Some([1, 2]),
+                nano_zone: Some(true)
+            }
+        );
+        assert_eq!(known.ptrauth_disabled, Some(false));
+        assert_eq!(known.arm64e_abi, Some(Arm64eAbi::All));
+        assert_eq!(known.vm_force_4k_pages, Some(true));
+        assert_eq!(known.dyld_cache_uuid.map(|u| u[15]), Some(0x3a));
+        assert_eq!(
+            known.other,
+            [(
+                OsStr::new("executable_file"),
+                OsStr::new("0x1a0100000f,0x71b112")
+            )]
+        );
+
+        assert_eq!(KnownArgs::from_args::<&[u8]>(&[]), KnownArgs::default());
+    }
+
+    #[test]
+    fn translation() {
+        use std::io::ErrorKind;
+
+        assert_eq!(translated_from(Ok(1)), Some(true));
+        assert_eq!(translated_from(Ok(0)), Some(false));
+        assert_eq!(translated_from(Err(ErrorKind::NotFound)), Some(false));
+        assert_eq!(translated_from(Err(ErrorKind::PermissionDenied)), None);
+    }
+
+    #[test]
+    fn file_names() {
+        // What `executable_name` returns for each `executable_path`.
+        fn parse_file_name(value: &[u8]) -> Option<&OsStr> {
+            parse_path(value).file_name()
+        }
+
+        assert_eq!(parse_file_name(b"/bin/ls"), Some(OsStr::new("ls")));
+        assert_eq!(parse_file_name(b"./App.app/App"), Some(OsStr::new("App")));
+        assert_eq!(parse_file_name(b"ls"), Some(OsStr::new("ls")));
+        assert_eq!(parse_file_name(b"/bin/ls/"), Some(OsStr::new("ls")));
+        assert_eq!(parse_file_name(b"/"), None);
+        assert_eq!(parse_file_name(b"/bin/.."), None);
+        assert_eq!(parse_file_name(b""), None);
+    }
+
+    #[test]
+    fn absolute_paths() {
+        let cwd = Path::new("/Users/me");
+
+        assert_eq!(absolute_in(Path::new("/bin/ls"), cwd), Path::new("/bin/ls"));
+        assert_eq!(
+            absolute_in(Path::new("./target/app"), cwd),
+            Path::new("/Users/me/target/app")
+        );
+        assert_eq!(
+            absolute_in(Path::new("../app"), cwd),
+            Path::new("/Users/me/../app")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "sysctl-fallback")]
+    fn path_fallback() {
+        let present = path_or_else(Some(b"./app"), || unreachable!("the argument is present"));
+        assert_eq!(present, Some(Path::new("./app")));
+
+        let fallback = path_or_else(None, || Some(Path::new("/bin/app")));
+        assert_eq!(fallback, Some(Path::new("/bin/app")));
+        assert_eq!(path_or_else(None, || None), None);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "touches the filesystem")]
+    fn executable_path_check() {
+        let dir = std::env::temp_dir().join(format!("appleargs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let exe = dir.join("exe");
+        let other = dir.join("other");
+        let link = dir.join("link");
+        std::fs::write(&exe, b"").unwrap();
+        std::fs::write(&other, b"").unwrap();
+        std::os::unix::fs::symlink(&exe, &link).unwrap();
+
+        assert_eq!(same_file(&exe, &exe), Some(true));
+        assert_eq!(same_file(&link, &exe), Some(true));
+        assert_eq!(same_file(&dir.join(".").join("exe"), &exe), Some(true));
+        assert_eq!(same_file(&other, &exe), Some(false));
+        assert_eq!(same_file(&dir.join("missing"), &exe), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "touches the filesystem")]
+    fn realpaths() {
+        let dir = std::env::temp_dir().join(format!("appleargs-realpath-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("bin")).unwrap();
+
+        let exe = dir.join("bin").join("exe");
+        let link = dir.join("link");
+        std::fs::write(&exe, b"").unwrap();
+        std::os::unix::fs::symlink(Path::new("bin").join("exe"), &link).unwrap();
+
+        let resolved = realpath_in(&exe).unwrap();
+        assert!(resolved.is_absolute());
+        assert!(resolved.ends_with("bin/exe"));
+        assert_eq!(realpath_in(&link), Some(resolved.clone()));
+        assert_eq!(realpath_in(&dir.join("bin/../link")), Some(resolved));
+        assert_eq!(realpath_in(&dir.join("missing")), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn present_keys() {
+        let args: &[&[u8]] = &[
+            b"executable_path=/bin/ls",
+            b"custom=1",
+            b"th_port=",
+            b"token",
+            b"executable_path=/bin/sh",
+            b"XPC_FLAGS=0x1",
+            b"Executable_path=/bin/zsh",
+        ];
+
+        let present: Vec<_> = present_in(args).collect();
+        assert_eq!(
+            present,
+            [
+                AppleArg::ExecutablePath,
+                AppleArg::ThPort,
+                AppleArg::XpcFlags
+            ]
+        );
+        assert_eq!(present_in::<&[u8]>(&[]).count(), 0);
+
+        for &arg in AppleArg::ALL {
+            assert_eq!(AppleArg::from_key(arg.key().as_bytes()), Some(arg));
+        }
+        assert_eq!(AppleArg::from_key(b"custom"), None);
+    }
+
+    #[test]
+    fn fixture_samples() {
+        use crate::testing::fixtures;
+
+        for (name, args) in fixtures::all_samples() {
+            let known = KnownArgs::from_args(args);
+
+            assert!(
+                known.executable_path.is_some_and(Path::is_absolute),
+                "{name}"
+            );
+            assert_eq!(known.th_port, Some(0x103), "{name}");
+            assert!(known.executable_cdhash.is_some(), "{name}");
+            assert!(known.executable_boothash.is_some(), "{name}");
+            assert!(known.dyld_file.is_some(), "{name}");
+            assert_eq!(known.arm64e_abi, Some(Arm64eAbi::Os), "{name}");
+            // Erased by libSystem, so present but unparseable.
+            assert_eq!(known.ptr_munge, None, "{name}");
+            assert_eq!(known.main_stack, None, "{name}");
+            assert_eq!(
+                known.other.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+                [OsStr::new("executable_file")],
+                "{name}"
+            );
+
+            for &arg in args {
+                let (key, value) = split_kv(arg).unwrap();
+                let Some(known) = AppleArg::from_key(key) else {
+                    continue;
+                };
+                if known.kind() != KnownKind::Raw && !value.is_empty() {
+                    assert!(typed(known, value).is_some(), "{name}: {key:?}");
+                }
+            }
+        }
+
+        let simulator = |args| detect_simulator(None, getenv_in(args, b"executable_path"));
+        assert_eq!(simulator(fixtures::macos_sample()), None);
+        assert_eq!(simulator(fixtures::ios_device_sample()), Some(false));
+        assert_eq!(simulator(fixtures::simulator_sample()), Some(true));
+    }
+
+    #[test]
+    fn typed_dispatch() {
+        let parse = |key: &[u8], value| AppleArg::from_key(key).and_then(|arg| typed(arg, value));
+
+        assert_eq!(parse(b"ptr_munge", b"0x1f"), Some(KnownValue::Hex(0x1f)));
+        assert_eq!(
+            parse(b"dyld_file", b"0x1a,0x71b112"),
+            Some(KnownValue::HexList(vec![0x1a, 0x71b112]))
+        );
+        assert_eq!(
+            parse(b"stack_guard", b"0x1,0x2"),
+            Some(KnownValue::Hex(0x1))
+        );
+        assert_eq!(AppleArg::StackGuard.kind(), KnownKind::FirstHex);
+        assert_eq!(parse(b"ptr_munge", b"0x1,0x2"), None);
+        assert_eq!(
+            parse(b"executable_path", b"/bin/ls"),
+            Some(KnownValue::Path(Path::new("/bin/ls")))
+        );
+        assert_eq!(parse(b"th_port", b"259"), Some(KnownValue::Port(259)));
+        assert_eq!(parse(b"th_port", b"0x103"), Some(KnownValue::Port(259)));
+        assert_eq!(
+            parse(b"MallocNanoZone", b"0"),
+            Some(KnownValue::Bool(false))
+        );
+        assert_eq!(
+            parse(b"arm64e_abi", b"os"),
+            Some(KnownValue::Raw(OsStr::new("os")))
+        );
+
+        assert_eq!(parse(b"ptr_munge", b"1f"), None);
+        assert_eq!(parse(b"main_stack", b"0x1,,0x2"), None);
+        assert_eq!(parse(b"th_port", b"port"), None);
+        assert_eq!(parse(b"ptrauth_disabled", b"yes"), None);
+        assert_eq!(parse(b"custom", b"0x1"), None);
+    }
+
+    #[test]
+    fn dyld_file_parsing() {
+        let args: &[&[u8]] = &[b"dyld_file=0x1a0100000f,0xfffffff000dc897"];
+        let value = getenv_in(args, b"dyld_file").unwrap();
+
+        assert_eq!(
+            FileId::parse(value),
+            Some(FileId {
+                fsid: 0x1a_0100_000f,
+                inode: 0xfff_ffff_000d_c897,
+            })
+        );
+        assert_eq!(FileId::parse(b"0x1a0100000f"), None);
+        assert_eq!(FileId::parse(b"/usr/lib/dyld"), None);
+    }
+
+    #[test]
+    fn xpc_service() {
+        let args: &'static [&'static [u8]] = &[
+            b"executable_path=/Applications/Demo.app/Contents/XPCServices/Helper.xpc/Contents/MacOS/Helper",
+            b"XPC_SERVICE_NAME=com.example.demo.helper",
+            b"XPC_FLAGS=0x1",
+            b"__XPC_DYLD_LIBRARY_PATH=/tmp/lib",
+            b"xpc_session=\xff",
+            b"XPCNAME=no",
+        ];
+
+        let known = KnownArgs::from_args(args);
+        assert_eq!(known.xpc_service_name, Some("com.example.demo.helper"));
+        assert_eq!(known.xpc_flags, Some(1));
+
+        let keys: Vec<_> = xpc_vars_in(args).map(|(k, _)| k).collect();
+        assert_eq!(
+            keys,
+            [
+                "XPC_SERVICE_NAME",
+                "XPC_FLAGS",
+                "__XPC_DYLD_LIBRARY_PATH",
+                "xpc_session"
+            ]
+        );
+        assert_eq!(
+            xpc_vars_in(args).next_back(),
+            Some((OsStr::new("xpc_session"), OsStr::from_bytes(b"\xff")))
+        );
+
+        assert_eq!(parse_str(b"\xff"), None);
+        assert_eq!(parse_hex(b"0x0"), Some(0));
+    }
+
+    #[test]
+    fn hardening_fields() {
+        let read = |args: &[&'static [u8]]| Hardening::read(|k| getenv_in(args, k));
+
+        assert_eq!(read(&[]), Hardening::default());
+
+        let full = read(&[
+            b"ptrauth_disabled=0",
+            b"arm64e_abi=all",
+            b"MallocNanoZone=1",
+        ]);
+        assert_eq!(
+            full,
+            Hardening {
+                ptrauth_disabled: Some(false),
+                arm64e_abi: Some(Arm64eAbi::All),
+                malloc_nano_zone: Some(true),
+            }
+        );
+
+        let garbage = read(&[
+            b"ptrauth_disabled=yes",
+            b"arm64e_abi=none",
+            b"MallocNanoZone=",
+        ]);
+        assert_eq!(garbage, Hardening::default());
+    }
+
+    #[test]
+    fn hw_hints() {
+        let args: &[&[u8]] = &[b"ptrauth_disabled=1", b"arm64e_abi=os"];
+        let get = |key: &[u8]| getenv_in(args, key);
+
+        assert_eq!(ptrauth_disabled_in(get), Some(true));
+        assert_eq!(arm64e_abi_in(get), Some(Arm64eAbi::Os));
+        assert_eq!(vm_force_4k_pages_in(get), None);
+
+        let args: &[&[u8]] = &[
+            b"ptrauth_disabled=yes",
+            b"arm64e_abi=all",
+            b"vm_force_4k_pages=0",
+        ];
+        let get = |key: &[u8]| getenv_in(args, key);
+
+        assert_eq!(ptrauth_disabled_in(get), None);
+        assert_eq!(arm64e_abi_in(get), Some(Arm64eAbi::All));
+        assert_eq!(vm_force_4k_pages_in(get), Some(false));
+        assert_eq!(arm64e_abi_in(|_| Some(b"plugin")), None);
+    }
+}
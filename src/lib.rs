@@ -2,14 +2,27 @@
 #![deny(missing_docs, clippy::undocumented_unsafe_blocks)]
 
 use core::iter::FusedIterator;
-use core::ptr::{self, NonNull};
-use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
-use std::ffi::OsStr;
-use std::os::raw::{c_char, c_int};
+use core::ptr;
+use std::ffi::{CStr, OsStr, OsString};
 use std::os::unix::prelude::OsStrExt;
+use std::path::Path;
 
-// todo: (target_os = "tvos", target_os = "watchos") after testing
-#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub mod build;
+pub mod env;
+pub mod known;
+mod sys;
+pub mod testing;
+
+use sys::args_slice;
+#[cfg(feature = "no-ctor")]
+pub use sys::init;
+#[cfg(feature = "init-hook")]
+pub use sys::on_init;
+pub use sys::{init_state, try_init_from_environ, InitState};
+
+// Every Apple OS gets apple arguments from its kernel, but only the ones listed in
+// `sys` are known to run our constructor. Others build, but see no arguments.
+#[cfg(not(target_vendor = "apple"))]
 compile_error!("appleargs is not supported on this platform");
 
 /// An iterator over the process' apple arguments.
@@ -18,13 +31,36 @@ compile_error!("appleargs is not supported on this platform");
 /// valid UTF-8.
 #[derive(Clone)]
 pub struct AppleArgs {
-    inner: core::slice::Iter<'static, Vec<u8>>,
+    inner: Remaining<&'static [u8]>,
+}
+
+impl AppleArgs {
+    /// Returns an iterator over no arguments.
+    ///
+    /// Unlike [`apple_args`], this is a `const fn`, so it can be used to initialize a
+    /// `const` or `static`, or as a placeholder in a default value.
+    #[inline]
+    pub const fn empty() -> Self {
+        Self {
+            inner: Remaining::EMPTY,
+        }
+    }
+
+    /// Returns the position of the first remaining argument which is exactly `needle`.
+    ///
+    /// This doesn't consume any arguments, and never panics, even if some aren't valid
+    /// UTF-8. Positions count from the next argument this would yield, so on a fresh
+    /// iterator they're indices into [`raw_apple_args`].
+    #[inline]
+    pub fn position_of(&self, needle: &str) -> Option<usize> {
+        position_in(&self.inner, needle.as_bytes())
+    }
 }
 
 impl core::fmt::Debug for AppleArgs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_list()
-            .entries(self.inner.clone().map(str_from_slice))
+            .entries(self.inner.clone().map(|v| DebugArg(v)))
             .finish()
     }
 }
@@ -33,8 +69,13 @@ impl Iterator for AppleArgs {
     type Item = &'static str;
 
     #[inline]
+    #[track_caller]
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(str_from_slice)
+        // Not `map`, so the panic location stays the caller's.
+        match self.inner.next() {
+            Some(arg) => Some(str_from_slice(arg)),
+            None => None,
+        }
     }
 
     #[inline]
@@ -57,8 +98,12 @@ impl ExactSizeIterator for AppleArgs {
 
 impl DoubleEndedIterator for AppleArgs {
     #[inline]
+    #[track_caller]
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner.next_back().map(str_from_slice)
+        match self.inner.next_back() {
+            Some(arg) => Some(str_from_slice(arg)),
+            None => None,
+        }
     }
 }
 
@@ -68,20 +113,123 @@ impl FusedIterator for AppleArgs {}
 ///
 /// The order of the arguments returned is not guaranteed, nor is the count, or the presence any specific item.
 ///
-/// See the top-level documentation's example of what this could return.
+/// See the top-level documentation's example of what this could return. Use [`init_state`]
+/// to tell apart having no arguments and the arguments not being captured yet.
 #[inline]
 pub fn apple_args() -> AppleArgs {
+    #[cfg(feature = "warn-on-empty")]
+    warn_if_not_captured();
+
     let inner = args_slice_iter();
 
     AppleArgs { inner }
 }
 
+/// Returns how many apple arguments the current process has, like C's `argc`.
+///
+/// This counts the arguments the iterators in this crate yield, so the empty ones
+/// passed by the loader aren't included unless the `keep-empty-args` feature is
+/// enabled. It's the same as
+/// [`env::count_all_args`], and is zero until the arguments are captured.
+#[inline]
+pub fn apple_argc() -> usize {
+    args_slice().len()
+}
+
 /// An iterator over the process' apple arguments.
 ///
 /// This iterator does not check that any argument is a valid UTF-8 string.
 #[derive(Clone)]
 pub struct AppleArgsOs {
-    inner: core::slice::Iter<'static, Vec<u8>>,
+    inner: Remaining<&'static [u8]>,
+}
+
+impl AppleArgsOs {
+    /// Returns an iterator over no arguments.
+    ///
+    /// Unlike [`apple_args_os`], this is a `const fn`, so it can be used to initialize a
+    /// `const` or `static`, or as a placeholder in a default value.
+    #[inline]
+    pub const fn empty() -> Self {
+        Self {
+            inner: Remaining::EMPTY,
+        }
+    }
+
+    /// Splits off the first remaining argument, returning it and an iterator over the
+    /// rest, like [`<[T]>::split_first`](slice::split_first).
+    ///
+    /// Returns `None` if no arguments are left. To peel off more than one argument,
+    /// take them through [`by_ref`](Iterator::by_ref) and keep using the iterator
+    /// afterwards:
+    ///
+    /// ```
+    /// let mut args = appleargs::apple_args_os();
+    /// let prefix: Vec<_> = args.by_ref().take(2).collect();
+    /// println!("{prefix:?}, then {} more", args.len());
+    /// ```
+    #[inline]
+    pub fn split_first(mut self) -> Option<(&'static OsStr, AppleArgsOs)> {
+        let first = self.next()?;
+        Some((first, self))
+    }
+
+    /// Returns the position of the first remaining argument which is exactly `needle`.
+    ///
+    /// See [`AppleArgs::position_of`] for what the positions count.
+    #[inline]
+    pub fn position_of(&self, needle: impl AsRef<OsStr>) -> Option<usize> {
+        position_in(&self.inner, needle.as_ref().as_bytes())
+    }
+
+    /// Reinterprets each remaining argument as a [`Path`].
+    ///
+    /// This is a blind conversion and not a filter: most apple arguments aren't paths,
+    /// so this is only useful when you already know which arguments you're looking at.
+    #[inline]
+    pub fn paths(
+        self,
+    ) -> impl DoubleEndedIterator<Item = &'static Path> + ExactSizeIterator + FusedIterator + Clone
+    {
+        self.map(Path::new)
+    }
+
+    /// Skips every remaining argument which starts with `prefix`.
+    ///
+    /// This matches the raw argument, so a prefix of `key=` drops the `key` pair, while
+    /// a prefix of just `key` also drops any other argument starting with it.
+    #[inline]
+    pub fn without_prefix<P>(
+        self,
+        prefix: P,
+    ) -> impl DoubleEndedIterator<Item = &'static OsStr> + FusedIterator + Clone
+    where
+        P: AsRef<OsStr> + Clone,
+    {
+        self.filter(move |arg| !arg.as_bytes().starts_with(prefix.as_ref().as_bytes()))
+    }
+
+    /// Pairs each remaining argument with its position in the original array
+    /// passed by the loader.
+    ///
+    /// These positions count the empty arguments which this crate drops, so they
+    /// may not be contiguous. With the `keep-empty-args` feature, nothing is dropped and
+    /// these are always contiguous.
+    #[inline]
+    pub fn indexed(
+        self,
+    ) -> impl DoubleEndedIterator<Item = (usize, &'static OsStr)>
+           + ExactSizeIterator
+           + FusedIterator
+           + Clone {
+        // Iterators from `empty` don't point into the arguments at all, so they get no
+        // indices, as they have no arguments.
+        let indices = front_offset(args_slice(), &self.inner)
+            .and_then(|start| sys::args_indices().get(start..)?.get(..self.len()))
+            .unwrap_or(&[]);
+
+        indices.iter().copied().zip(self)
+    }
 }
 
 impl core::fmt::Debug for AppleArgsOs {
@@ -127,103 +275,527 @@ impl DoubleEndedIterator for AppleArgsOs {
 
 impl FusedIterator for AppleArgsOs {}
 
+/// An iterator over the process' apple arguments, as raw bytes.
+///
+/// The bytes don't include the nul terminator and are never checked to be UTF-8.
+#[derive(Clone)]
+pub struct AppleArgsBytes {
+    inner: Remaining<&'static [u8]>,
+}
+
+impl AppleArgsBytes {
+    /// Returns an iterator over no arguments.
+    ///
+    /// Unlike [`apple_args_bytes`], this is a `const fn`, so it can be used to initialize a
+    /// `const` or `static`, or as a placeholder in a default value.
+    #[inline]
+    pub const fn empty() -> Self {
+        Self {
+            inner: Remaining::EMPTY,
+        }
+    }
+}
+
+impl core::fmt::Debug for AppleArgsBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.inner.clone().map(|v| DebugArg(v)))
+            .finish()
+    }
+}
+
+impl Iterator for AppleArgsBytes {
+    type Item = &'static [u8];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().copied()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl ExactSizeIterator for AppleArgsBytes {
+    #[inline]
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl DoubleEndedIterator for AppleArgsBytes {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().copied()
+    }
+}
+
+impl FusedIterator for AppleArgsBytes {}
+
+/// An iterator over the process' apple arguments, as nul-terminated [`CStr`]s.
+///
+/// Each item has the same bytes as the matching one from [`AppleArgsBytes`], followed
+/// by its nul terminator, so it can be handed straight to C.
+#[derive(Clone)]
+pub struct AppleArgsCStr {
+    inner: Remaining<&'static CStr>,
+}
+
+impl AppleArgsCStr {
+    /// Returns an iterator over no arguments.
+    ///
+    /// Unlike [`apple_args_cstr`], this is a `const fn`, so it can be used to initialize a
+    /// `const` or `static`, or as a placeholder in a default value.
+    #[inline]
+    pub const fn empty() -> Self {
+        Self {
+            inner: Remaining::EMPTY,
+        }
+    }
+}
+
+impl core::fmt::Debug for AppleArgsCStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.inner.clone().map(|v| DebugArg(v.to_bytes())))
+            .finish()
+    }
+}
+
+impl Iterator for AppleArgsCStr {
+    type Item = &'static CStr;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().copied()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl ExactSizeIterator for AppleArgsCStr {
+    #[inline]
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl DoubleEndedIterator for AppleArgsCStr {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().copied()
+    }
+}
+
+impl FusedIterator for AppleArgsCStr {}
+
 /// Returns the Apple arguments of the current process.
 ///
 /// The order of the arguments returned is not guaranteed, nor is the count, or the presence any specific item.
 ///
-/// See the top-level documentation's example of what this could return.
+/// See the top-level documentation's example of what this could return. Use [`init_state`]
+/// to tell apart having no arguments and the arguments not being captured yet.
 #[inline]
 pub fn apple_args_os() -> AppleArgsOs {
+    #[cfg(feature = "warn-on-empty")]
+    warn_if_not_captured();
+
     let inner = args_slice_iter();
 
     AppleArgsOs { inner }
 }
 
-#[allow(clippy::ptr_arg)]
-fn str_from_slice(bytes: &Vec<u8>) -> &str {
-    core::str::from_utf8(bytes).expect("apple argument was not valid UTF-8")
-}
-
-fn args_slice_iter() -> core::slice::Iter<'static, Vec<u8>> {
-    // This synchronizes with the `Release` store and acts as a fence.
-    let data = ARGS_DATA.load(Ordering::Acquire);
-
-    NonNull::new(data)
-        .map(|ptr| {
-            // `Relaxed` is fine because it is fenced by the `Acquire` used
-            // for `data` and `len` is written prior to storing `data`.
-            let len = ARGS_LEN.load(Ordering::Relaxed);
-            // Safety: `ptr` is always a valid slice and `len` always matches
-            // because of the orderings.
-            unsafe { core::slice::from_raw_parts(ptr.as_ptr(), len) }
-        })
-        .unwrap_or(&[])
-        .iter()
-}
-
-static ARGS_DATA: AtomicPtr<Vec<u8>> = AtomicPtr::new(ptr::null_mut());
-static ARGS_LEN: AtomicUsize = AtomicUsize::new(0);
-
-unsafe extern "C" fn init_function(
-    _argc: c_int,
-    _argv: *const *const c_char,
-    _envp: *const *const c_char,
-    mut applep: *const *const c_char,
-) {
-    let mut v: Vec<Vec<u8>> = Vec::new();
-
-    // Safety: `applep` is not null, so its valid to read another pointer from.
-    while !applep.is_null() && !applep.read().is_null() {
-        // Safety: See above
-        let p: *const i8 = applep.read();
-
-        // Safety: `applep` was pointing at a valid nul-terminated
-        // string.
-        let len = strlen(p);
-        let ptr = p as *const u8;
-        let s = core::slice::from_raw_parts(ptr, len); // Explicit nul skip.
-
-        if !s.is_empty() {
-            v.push(s.to_owned());
+/// Returns the Apple arguments of the current process, as raw bytes.
+///
+/// See [`apple_args_os`] for the guarantees about ordering and contents, and
+/// [`raw_apple_args`] for the same arguments as a slice.
+#[inline]
+pub fn apple_args_bytes() -> AppleArgsBytes {
+    #[cfg(feature = "warn-on-empty")]
+    warn_if_not_captured();
+
+    AppleArgsBytes {
+        inner: args_slice_iter(),
+    }
+}
+
+/// Returns the Apple arguments of the current process as [`CStr`]s, for passing them
+/// back to C without copying.
+///
+/// The arguments are copied along with their nul terminators when they're captured,
+/// and every other iterator borrows from those copies without the nul, so this doesn't
+/// allocate. See [`apple_args_os`] for the guarantees about ordering and contents.
+#[inline]
+pub fn apple_args_cstr() -> AppleArgsCStr {
+    #[cfg(feature = "warn-on-empty")]
+    warn_if_not_captured();
+
+    AppleArgsCStr {
+        inner: Remaining(sys::args_cstrs()),
+    }
+}
+
+/// Returns the Apple arguments of the current process as owned [`OsString`]s, for APIs
+/// which want owned values, like [`Command::args`](std::process::Command::args).
+///
+/// Each argument is copied into a new allocation as it's yielded. Prefer
+/// [`apple_args_os`] when borrowed values will do.
+#[inline]
+pub fn apple_args_os_owned(
+) -> impl DoubleEndedIterator<Item = OsString> + ExactSizeIterator + FusedIterator {
+    apple_args_os().map(OsStr::to_os_string)
+}
+
+/// Returns the Apple arguments of the current process as a slice of raw byte strings.
+///
+/// This is the same storage the iterators in this crate walk over, for when you'd rather
+/// run your own algorithm over the whole array. The strings don't include their nul
+/// terminators, and the empty ones passed by the loader are left out.
+///
+/// With the `keep-empty-args` feature, the empty ones are kept as `b""`, so this mirrors
+/// the loader's array exactly. The [`env`](mod@env) lookups skip them either way, as
+/// they aren't `key=value` pairs.
+///
+/// The slice is empty until the arguments are captured, see [`init_state`]. After that
+/// it never changes, and both it and the strings in it live for the rest of the program.
+#[inline]
+pub fn raw_apple_args() -> &'static [&'static [u8]] {
+    args_slice()
+}
+
+/// Decodes one of the arguments from [`raw_apple_args`] as UTF-8, keeping the `'static`
+/// lifetime.
+///
+/// This is what [`apple_args`] does for each argument, except that it reports invalid
+/// UTF-8 instead of panicking.
+///
+/// # Errors
+///
+/// Returns the [`Utf8Error`](core::str::Utf8Error) if `bytes` isn't valid UTF-8.
+#[inline]
+pub fn decode_arg(bytes: &'static [u8]) -> Result<&'static str, core::str::Utf8Error> {
+    core::str::from_utf8(bytes)
+}
+
+/// Returns the total length in bytes of every apple argument, not counting their nul
+/// terminators.
+///
+/// This is handy for sizing a buffer before copying the arguments somewhere else.
+#[inline]
+pub fn total_len() -> usize {
+    total_len_in(args_slice())
+}
+
+fn total_len_in(args: &[&[u8]]) -> usize {
+    args.iter().map(|arg| arg.len()).sum()
+}
+
+/// Formats every apple argument into a string, one per line, for attaching to bug
+/// reports and the like.
+///
+/// `key=value` arguments are written as they are, and other arguments are prefixed with
+/// `[token] ` to tell them apart. Invalid UTF-8 is replaced, so this never panics.
+pub fn dump_string() -> String {
+    dump_in(args_slice(), false)
+}
+
+/// Like [`dump_string`], but with the `key=value` arguments sorted by key, for output
+/// which is stable across launches. Other arguments come last, in their original order.
+pub fn dump_string_sorted() -> String {
+    dump_in(args_slice(), true)
+}
+
+fn dump_in(args: &[&[u8]], sorted: bool) -> String {
+    let mut lines = args.to_vec();
+    if sorted {
+        // Stable, so tokens and repeated keys keep their order.
+        lines.sort_by_key(|arg| env::split_kv(arg).map(|(k, _)| k).ok_or(()));
+    }
+
+    let mut out = String::new();
+    for arg in lines {
+        if env::split_kv(arg).is_none() {
+            out.push_str("[token] ");
+        }
+        out.push_str(&String::from_utf8_lossy(arg));
+        out.push('\n');
+    }
+    out
+}
+
+/// Checks that every apple argument is valid UTF-8, which means [`apple_args`] won't panic.
+///
+/// # Errors
+///
+/// Returns the position of the first invalid argument, as [`apple_args`] would yield it,
+/// along with why it isn't valid.
+pub fn validate_utf8() -> Result<(), (usize, core::str::Utf8Error)> {
+    validate_utf8_in(args_slice())
+}
+
+fn validate_utf8_in(args: &[&[u8]]) -> Result<(), (usize, core::str::Utf8Error)> {
+    args.iter().enumerate().try_fold((), |(), (idx, arg)| {
+        core::str::from_utf8(arg).map(drop).map_err(|e| (idx, e))
+    })
+}
+
+/// A rewindable iterator over the process' apple arguments.
+///
+/// This behaves like [`AppleArgsOs`], but can be [reset](Self::reset) back to the
+/// first argument, which is handy for scanning the arguments repeatedly in a hot loop.
+#[derive(Clone)]
+pub struct AppleArgsCursor {
+    all: &'static [&'static [u8]],
+    inner: core::slice::Iter<'static, &'static [u8]>,
+}
+
+impl AppleArgsCursor {
+    /// Returns a cursor positioned at the first apple argument.
+    #[inline]
+    pub fn new() -> Self {
+        let all = args_slice();
+
+        Self {
+            all,
+            inner: all.iter(),
+        }
+    }
+
+    /// Rewinds the cursor to the first argument.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.inner = self.all.iter();
+    }
+}
+
+impl Default for AppleArgsCursor {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for AppleArgsCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.inner.clone().map(|v| OsStr::from_bytes(v)))
+            .finish()
+    }
+}
+
+impl Iterator for AppleArgsCursor {
+    type Item = &'static OsStr;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|v| OsStr::from_bytes(v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for AppleArgsCursor {
+    #[inline]
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl DoubleEndedIterator for AppleArgsCursor {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|v| OsStr::from_bytes(v))
+    }
+}
+
+impl FusedIterator for AppleArgsCursor {}
+
+/// Decodes an argument, panicking at the caller's location if it isn't UTF-8.
+#[inline]
+#[track_caller]
+fn str_from_slice(bytes: &&'static [u8]) -> &'static str {
+    match core::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => invalid_utf8(bytes),
+    }
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn invalid_utf8(bytes: &[u8]) -> ! {
+    // Arguments always come from the global slice, so the index can be recovered
+    // without having to thread it through the iterators.
+    match args_slice().iter().position(|arg| ptr::eq(*arg, bytes)) {
+        Some(idx) => panic!("apple argument {idx} not valid UTF-8: {}", HexDump(bytes)),
+        None => panic!("apple argument not valid UTF-8: {}", HexDump(bytes)),
+    }
+}
+
+/// Formats an argument as a string, or as a byte string if it isn't valid UTF-8,
+/// so that debugging never panics.
+pub(crate) struct DebugArg<'a>(pub(crate) &'a [u8]);
+
+impl core::fmt::Debug for DebugArg<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match core::str::from_utf8(self.0) {
+            Ok(s) => core::fmt::Debug::fmt(s, f),
+            Err(_) => write!(f, "b\"{}\"", self.0.escape_ascii()),
+        }
+    }
+}
+
+/// Formats the start of a byte string like `[0xff, 0x00, ...]`.
+struct HexDump<'a>(&'a [u8]);
+
+impl core::fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        const MAX_SHOWN: usize = 16;
+
+        f.write_str("[")?;
+        for (i, byte) in self.0.iter().take(MAX_SHOWN).enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{byte:#04x}")?;
+        }
+        if self.0.len() > MAX_SHOWN {
+            f.write_str(", ...")?;
+        }
+        f.write_str("]")
+    }
+}
+
+fn position_in(iter: &Remaining<&'static [u8]>, needle: &[u8]) -> Option<usize> {
+    iter.as_slice().iter().position(|arg| *arg == needle)
+}
+
+/// Prints a warning the first time the arguments are read before being captured, as
+/// that's almost always a misconfiguration.
+#[cfg(feature = "warn-on-empty")]
+fn warn_if_not_captured() {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    static WARNED: AtomicBool = AtomicBool::new(false);
+
+    // The constructor is only registered on these, see `sys`.
+    if cfg!(not(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "tvos",
+        target_os = "watchos",
+        target_os = "visionos"
+    ))) || init_state() != InitState::NotRun
+    {
+        return;
+    }
+
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        if cfg!(feature = "no-ctor") {
+            eprintln!(
+                "appleargs: the apple arguments were read before `appleargs::init` was called"
+            );
+        } else {
+            eprintln!(
+                "appleargs: the apple arguments were read before being captured. If this isn't \
+                 running from another static constructor, check that the linker didn't strip \
+                 the `__mod_init_func` section or this crate's constructor."
+            );
         }
+    }
+}
+
+/// Returns how many arguments have been consumed from the front of `iter`, or `None` if
+/// it isn't iterating over `all`.
+fn front_offset(all: &[&[u8]], iter: &Remaining<&'static [u8]>) -> Option<usize> {
+    let all = all.as_ptr_range();
+    let rest = iter.as_slice().as_ptr_range();
+    if rest.start < all.start || rest.end > all.end {
+        return None;
+    }
+
+    Some((rest.start as usize - all.start as usize) / core::mem::size_of::<&[u8]>())
+}
+
+fn args_slice_iter() -> Remaining<&'static [u8]> {
+    Remaining(args_slice())
+}
+
+/// The part of an argument array an iterator hasn't yielded yet.
+///
+/// This works like a [`core::slice::Iter`], but it can be made in a `const`, which lets
+/// the iterators have `const` empty constructors.
+#[derive(Clone)]
+pub(crate) struct Remaining<T: 'static>(pub(crate) &'static [T]);
+
+impl<T> Remaining<T> {
+    pub(crate) const EMPTY: Self = Remaining(&[]);
+
+    #[inline]
+    pub(crate) fn as_slice(&self) -> &'static [T] {
+        self.0
+    }
+}
 
-        // Safety: This will never wrap and after incrementing
-        // past the last array element, the loop will stop.
-        applep = applep.add(1);
+impl<T> Iterator for Remaining<T> {
+    type Item = &'static T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (first, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(first)
     }
 
-    // `Relaxed` is fine because the store of `data` with
-    // `Release` acts as a fence, and `len` is always loaded
-    // after `data`.
-    ARGS_LEN.store(v.len(), Ordering::Relaxed);
-    ARGS_DATA.store(
-        Box::into_raw(v.into_boxed_slice()).cast::<Vec<u8>>(),
-        Ordering::Release,
-    );
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len(), Some(self.0.len()))
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.0 = self.0.get(n..).unwrap_or(&[]);
+        self.next()
+    }
 }
 
-extern "C" {
-    /// Provided by libc or compiler_builtins.
-    fn strlen(s: *const c_char) -> usize;
+impl<T> ExactSizeIterator for Remaining<T> {}
+
+impl<T> DoubleEndedIterator for Remaining<T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (last, rest) = self.0.split_last()?;
+        self.0 = rest;
+        Some(last)
+    }
 }
 
-#[used]
-#[cfg_attr(
-    any(target_os = "macos", target_os = "ios"),
-    link_section = "__DATA,__mod_init_func"
-)]
-static CTOR: unsafe extern "C" fn(
-    argc: c_int,
-    argv: *const *const c_char,
-    envp: *const *const c_char,
-    applep: *const *const c_char,
-) = init_function;
+impl<T> FusedIterator for Remaining<T> {}
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[cfg_attr(feature = "no-ctor", ignore = "needs the constructor")]
     fn smoke_check() {
         let args = apple_args();
         assert_ne!(args.clone().count(), 0);
@@ -234,5 +806,282 @@ mod tests {
 
         let args = apple_args_os();
         assert_ne!(!args.count(), 0);
+        assert_eq!(apple_argc(), apple_args().len());
+        assert!(env::total_env_len() <= total_len());
+
+        assert!(apple_args_os_owned().eq(apple_args_os()));
+        assert!(apple_args_bytes().eq(apple_args_os().map(OsStrExt::as_bytes)));
+        assert!(apple_args_cstr()
+            .map(CStr::to_bytes)
+            .zip(apple_args_bytes())
+            .all(|(cstr, bytes)| ptr::eq(cstr, bytes)));
+        assert_eq!(apple_args_cstr().len(), apple_args_bytes().len());
+        let paths = apple_args_os().paths();
+        assert_eq!(paths.len(), apple_args_os().len());
+        assert_eq!(raw_apple_args().len(), apple_args_os().len());
+        assert_eq!(validate_utf8(), Ok(()));
+
+        let indices: Vec<usize> = apple_args_os().indexed().map(|(i, _)| i).collect();
+        assert_eq!(indices.len(), apple_args_os().len());
+        assert!(indices.windows(2).all(|w| w[0] < w[1]));
+
+        assert!(env::apple_var("executable_path").is_ok());
+        assert!(env::apple_has_var("executable_path"));
+        assert!(!env::apple_has_var("appleargs_missing"));
+        assert_eq!(
+            env::apple_var_or_else("executable_path", || unreachable!()),
+            env::apple_var("executable_path").unwrap()
+        );
+        assert_eq!(
+            env::apple_var_or_else("appleargs_missing", || "default"),
+            "default"
+        );
+        assert_eq!(
+            env::apple_var_os_or_else("executable_path", || unreachable!()),
+            env::apple_var_os("executable_path").unwrap()
+        );
+        assert_eq!(
+            env::apple_var_os_or_else("appleargs_missing", || OsStr::new("default")),
+            "default"
+        );
+        assert_eq!(
+            env::apple_var_bytes("executable_path"),
+            env::apple_var_os("executable_path").map(OsStrExt::as_bytes)
+        );
+        assert_eq!(
+            env::apple_var_cstr(c"executable_path"),
+            env::apple_var_bytes("executable_path")
+        );
+        assert_eq!(env::apple_vars().count(), env::count_keys());
+        assert_eq!(env::apple_vars_os().rev().count(), env::count_keys());
+        assert_eq!(env::count_all_args(), apple_args().len());
+
+        #[cfg(feature = "init-hook")]
+        {
+            static RAN: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+            on_init(|| RAN.store(true, core::sync::atomic::Ordering::Relaxed));
+            assert!(RAN.load(core::sync::atomic::Ordering::Relaxed));
+        }
+
+        assert_ne!(known::verify_stack_guard(), Some(false));
+        assert_eq!(
+            known::executable_path_buf().as_deref(),
+            known::executable_path()
+        );
+        // Miri doesn't allow finding the current executable.
+        #[cfg(not(miri))]
+        assert_ne!(known::verify_executable_path(), Some(false));
+        #[cfg(not(miri))]
+        assert!(known::is_translated().is_some());
+        assert_eq!(init_state(), InitState::Ran(apple_args().len()));
+    }
+
+    #[test]
+    fn debug_invalid_utf8() {
+        let args: &'static [&'static [u8]] = &[b"ok=1", b"\xff\""];
+        let args = AppleArgs {
+            inner: Remaining(args),
+        };
+
+        assert_eq!(format!("{args:?}"), r#"["ok=1", b"\xff\""]"#);
+    }
+
+    #[test]
+    fn dumps() {
+        let args: &[&[u8]] = &[b"b=2", b"token", b"a=\xff", b"=empty", b"b=1", b"c"];
+
+        assert_eq!(
+            dump_in(args, false),
+            "b=2\n[token] token\na=\u{fffd}\n=empty\nb=1\n[token] c\n"
+        );
+        assert_eq!(
+            dump_in(args, true),
+            "=empty\na=\u{fffd}\nb=2\nb=1\n[token] token\n[token] c\n"
+        );
+        assert_eq!(dump_in(&[], true), "");
+    }
+
+    #[test]
+    fn utf8_validation() {
+        assert_eq!(validate_utf8_in(&[b"a=1", b"b"]), Ok(()));
+        assert_eq!(validate_utf8_in(&[]), Ok(()));
+
+        let (idx, err) = validate_utf8_in(&[b"a=1", b"b", b"c=\xff", b"\xfe"]).unwrap_err();
+        assert_eq!(idx, 2);
+        assert_eq!(err.valid_up_to(), 2);
+    }
+
+    #[test]
+    fn decoding() {
+        assert_eq!(
+            decode_arg(b"executable_path=/bin/ls"),
+            Ok("executable_path=/bin/ls")
+        );
+        assert_eq!(decode_arg(b""), Ok(""));
+        assert_eq!(decode_arg(b"a=\xff").unwrap_err().valid_up_to(), 2);
+    }
+
+    #[test]
+    fn total_lengths() {
+        assert_eq!(total_len_in(&[b"a=1", b"token", b"\xff"]), 9);
+        assert_eq!(total_len_in(&[]), 0);
+    }
+
+    #[test]
+    fn positions() {
+        let args: &'static [&'static [u8]] = &[b"a=1", b"-flag", b"\xff", b"-flag"];
+        let mut args = AppleArgs {
+            inner: Remaining(args),
+        };
+
+        assert_eq!(args.position_of("-flag"), Some(1));
+        assert_eq!(args.position_of("a"), None);
+        assert_eq!(args.position_of("missing"), None);
+        assert_eq!(args.len(), 4);
+
+        args.next();
+        assert_eq!(args.position_of("-flag"), Some(0));
+
+        let os = AppleArgsOs { inner: args.inner };
+        assert_eq!(os.position_of(OsStr::from_bytes(b"\xff")), Some(1));
+        assert_eq!(os.position_of("a=1"), None);
+    }
+
+    #[test]
+    fn head_tail() {
+        let args: &'static [&'static [u8]] = &[b"a=1", b"token", b"b=2"];
+
+        let (head, tail) = AppleArgsOs {
+            inner: Remaining(args),
+        }
+        .split_first()
+        .unwrap();
+        assert!(ptr::eq(head.as_bytes(), args[0]));
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail.clone().collect::<Vec<_>>(), ["token", "b=2"]);
+
+        let (head, tail) = tail.split_first().unwrap();
+        assert_eq!(head, "token");
+        let (head, tail) = tail.split_first().unwrap();
+        assert_eq!(head, "b=2");
+        assert!(tail.split_first().is_none());
+    }
+
+    #[test]
+    fn prefix_filter() {
+        let args: &'static [&'static [u8]] = &[b"a=1", b"internal_flag=1", b"internal", b"b=2"];
+        let args = AppleArgsOs {
+            inner: Remaining(args),
+        };
+
+        let kept = args.clone().without_prefix("internal");
+        assert_eq!(kept.clone().collect::<Vec<_>>(), ["a=1", "b=2"]);
+        assert_eq!(kept.clone().next_back(), Some(OsStr::new("b=2")));
+
+        let kept = args.without_prefix(OsStr::new("internal_flag="));
+        assert_eq!(kept.collect::<Vec<_>>(), ["a=1", "internal", "b=2"]);
+    }
+
+    #[test]
+    fn raw_bytes() {
+        let args: &'static [&'static [u8]] = &[b"a=1", b"\xff", b"token"];
+        let mut bytes = AppleArgsBytes {
+            inner: Remaining(args),
+        };
+
+        assert_eq!(format!("{bytes:?}"), r#"["a=1", b"\xff", "token"]"#);
+        assert_eq!(bytes.len(), 3);
+        assert!(ptr::eq(bytes.next().unwrap(), args[0]));
+        assert_eq!(bytes.next_back(), Some(&b"token"[..]));
+        assert_eq!(bytes.collect::<Vec<_>>(), [&b"\xff"[..]]);
+    }
+
+    #[test]
+    fn cstrs() {
+        let args: &'static [&'static CStr] = &[c"a=1", c"", c"token"];
+        let mut cstrs = AppleArgsCStr {
+            inner: Remaining(args),
+        };
+
+        assert_eq!(format!("{cstrs:?}"), r#"["a=1", "", "token"]"#);
+        assert_eq!(cstrs.len(), 3);
+        let first = cstrs.next().unwrap();
+        assert!(ptr::eq(first, args[0]));
+        assert_eq!(first.to_bytes_with_nul(), b"a=1\0");
+        assert_eq!(cstrs.next_back().map(CStr::to_bytes), Some(&b"token"[..]));
+        assert_eq!(cstrs.collect::<Vec<_>>(), [c""]);
+    }
+
+    #[test]
+    fn const_empty() {
+        const ARGS: AppleArgs = AppleArgs::empty();
+        static ARGS_OS: AppleArgsOs = AppleArgsOs::empty();
+
+        assert_eq!(ARGS.count(), 0);
+        assert_eq!(ARGS_OS.clone().count(), 0);
+        assert_eq!(AppleArgsBytes::empty().count(), 0);
+        assert_eq!(AppleArgsCStr::empty().count(), 0);
+        assert_eq!(AppleArgsOs::empty().next_back(), None);
+        assert_eq!(AppleArgsOs::empty().indexed().count(), 0);
+        assert_eq!(format!("{:?}", AppleArgs::empty()), "[]");
+    }
+
+    #[test]
+    fn empty_indexed() {
+        let mut indexed = AppleArgsOs::empty().indexed();
+        assert_eq!(indexed.len(), 0);
+        assert_eq!(indexed.next(), None);
+        assert_eq!(indexed.next_back(), None);
+    }
+
+    #[test]
+    fn front_offsets() {
+        let all: &'static [&'static [u8]] = &[b"a", b"b", b"c"];
+        let other: &'static [&'static [u8]] = &[b"x", b"y", b"z"];
+
+        let mut iter = Remaining(all);
+        assert_eq!(front_offset(all, &iter), Some(0));
+        iter.next();
+        iter.next_back();
+        assert_eq!(front_offset(all, &iter), Some(1));
+        iter.next();
+        assert_eq!(front_offset(all, &iter), Some(2));
+
+        assert_eq!(front_offset(all, &Remaining(&other[1..])), None);
+        assert_eq!(front_offset(&all[1..], &Remaining(all)), None);
+        assert_eq!(front_offset(all, &Remaining::EMPTY), None);
+    }
+
+    #[test]
+    fn cursor_reset() {
+        let all: &'static [&'static [u8]] = &[b"a", b"b"];
+        let mut cursor = AppleArgsCursor {
+            all,
+            inner: all.iter(),
+        };
+
+        assert_eq!(cursor.next(), Some(OsStr::new("a")));
+        assert_eq!(cursor.len(), 1);
+        cursor.reset();
+        assert_eq!(cursor.by_ref().count(), 2);
+        assert_eq!(cursor.next(), None);
+        cursor.reset();
+        assert_eq!(cursor.next_back(), Some(OsStr::new("b")));
+    }
+
+    #[test]
+    fn hex_dump() {
+        assert_eq!(HexDump(b"").to_string(), "[]");
+        assert_eq!(HexDump(b"\xff\x00a").to_string(), "[0xff, 0x00, 0x61]");
+
+        let long = HexDump(&[0xff; 17]).to_string();
+        assert!(long.ends_with("0xff, 0xff, ...]"));
+        assert_eq!(long.matches("0xff").count(), 16);
+    }
+
+    #[test]
+    #[should_panic(expected = "apple argument not valid UTF-8: [0x61, 0xff]")]
+    fn invalid_utf8_message() {
+        str_from_slice(&&b"a\xff"[..]);
     }
 }
@@ -0,0 +1,772 @@
+//! Capturing the apple arguments from the loader.
+
+use core::ptr;
+#[cfg(test)]
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+#[cfg(not(any(feature = "no-ctor", miri)))]
+use std::os::raw::c_int;
+
+/// The ordering used to load `CAPTURED` on the read path.
+///
+/// By default this synchronizes with the `Release` store and acts as a fence.
+///
+/// With `unsync-read` the fence is skipped, which makes this a plain load. This relies on
+/// every read happening after the load-time constructor has finished: constructors run
+/// on the main thread before `main`, and any other thread is spawned after that, which
+/// already orders the constructor's writes before anything the thread does. With
+/// `no-ctor`, [`init`] makes callers promise the same.
+///
+/// `lazy-parse` moves the writes to whichever thread reads first, so `unsync-read` has
+/// no effect with it.
+const READ_ORDERING: Ordering = if cfg!(all(feature = "unsync-read", not(feature = "lazy-parse"))) {
+    Ordering::Relaxed
+} else {
+    Ordering::Acquire
+};
+
+/// Everything captured from `applep`, published at once through [`CAPTURED`].
+#[derive(Debug)]
+struct Captured {
+    args: &'static [&'static [u8]],
+    /// The same arguments as `args`, including their nul terminators.
+    ///
+    /// Each argument is copied once, with its nul, and `args` borrows each of these
+    /// without it. Slices can't be read past their end, so the nul can't be found again
+    /// from `args` alone, which is why this keeps a second set of pointers instead.
+    cstrs: &'static [&'static CStr],
+    /// The position of each of `args` in the original `applep` array.
+    indices: &'static [usize],
+}
+
+/// Null until the arguments are captured, and then a leaked [`Captured`] which is
+/// never modified or freed.
+///
+/// Keeping everything behind a single pointer means a reader sees either all of a
+/// capture or none of it, no matter the order its parts were written in.
+static CAPTURED: AtomicPtr<Captured> = AtomicPtr::new(ptr::null_mut());
+static STORE_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Loads `CAPTURED`, which every reader goes through.
+#[inline]
+fn load(order: Ordering) -> Option<&'static Captured> {
+    #[cfg(all(miri, not(feature = "no-ctor")))]
+    miri::init();
+
+    #[cfg(all(feature = "lazy-parse", not(feature = "no-ctor")))]
+    {
+        if let Some(captured) = load_from(&CAPTURED, order) {
+            return Some(captured);
+        }
+        lazy::parse();
+    }
+
+    load_from(&CAPTURED, order)
+}
+
+/// An atomic pointer a [`Captured`] can be published through.
+///
+/// `CAPTURED` has to be a `core` atomic to be a `static`, but this lets the loom tests
+/// run the same publishing and loading code on loom's atomics.
+trait Slot {
+    fn load(&self, order: Ordering) -> *mut Captured;
+    fn store(&self, captured: *mut Captured, order: Ordering);
+}
+
+impl Slot for AtomicPtr<Captured> {
+    #[inline]
+    fn load(&self, order: Ordering) -> *mut Captured {
+        self.load(order)
+    }
+
+    fn store(&self, captured: *mut Captured, order: Ordering) {
+        self.store(captured, order)
+    }
+}
+
+#[cfg(all(loom, test))]
+impl Slot for loom::sync::atomic::AtomicPtr<Captured> {
+    fn load(&self, order: Ordering) -> *mut Captured {
+        self.load(order)
+    }
+
+    fn store(&self, captured: *mut Captured, order: Ordering) {
+        self.store(captured, order)
+    }
+}
+
+#[inline]
+fn load_from(slot: &impl Slot, order: Ordering) -> Option<&'static Captured> {
+    // Safety: `slot` only ever holds null or a leaked `Captured`, which was fully
+    // written before being stored with `Release`. See `READ_ORDERING` for when a
+    // `Relaxed` load is enough.
+    unsafe { slot.load(order).as_ref() }
+}
+
+fn publish(slot: &impl Slot, captured: Captured) {
+    let captured: *mut Captured = Box::leak(Box::new(captured));
+    slot.store(captured, Ordering::Release);
+}
+
+pub(crate) fn args_slice() -> &'static [&'static [u8]] {
+    load(READ_ORDERING).map_or(&[], |captured| captured.args)
+}
+
+/// Returns the same arguments as [`args_slice`], as [`CStr`]s.
+pub(crate) fn args_cstrs() -> &'static [&'static CStr] {
+    load(READ_ORDERING).map_or(&[], |captured| captured.cstrs)
+}
+
+/// Whether this crate has captured the apple arguments yet, as returned by [`init_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InitState {
+    /// The arguments haven't been captured yet.
+    ///
+    /// Outside of the `no-ctor` feature, this means the constructor didn't run, which
+    /// usually means it was stripped by the linker or the code reading the arguments is
+    /// itself running from an earlier constructor.
+    NotRun,
+    /// The arguments were captured, but there weren't any.
+    ///
+    /// This includes when the loader didn't pass an apple arguments array at all.
+    RanEmpty,
+    /// The arguments were captured, and this many were found.
+    Ran(usize),
+}
+
+/// Returns whether the apple arguments have been captured yet.
+///
+/// All the iterators in this crate are empty both before the arguments are captured and
+/// when there weren't any, so this can tell those two cases apart.
+#[inline]
+pub fn init_state() -> InitState {
+    // Always `Acquire`, so this can be relied on to order other reads after it.
+    match load(Ordering::Acquire) {
+        None => InitState::NotRun,
+        Some(captured) if captured.args.is_empty() => InitState::RanEmpty,
+        Some(captured) => InitState::Ran(captured.args.len()),
+    }
+}
+
+/// Returns the position of each argument in the original `applep` array, which can
+/// differ from its position in [`args_slice`] because empty strings are dropped,
+/// unless the `keep-empty-args` feature is enabled.
+pub(crate) fn args_indices() -> &'static [usize] {
+    load(READ_ORDERING).map_or(&[], |captured| captured.indices)
+}
+
+/// Captures the apple arguments from `applep`, the fourth parameter the loader
+/// passes to `main` and static constructors.
+///
+/// This is only available with the `no-ctor` feature, which stops this crate from
+/// registering a load-time constructor to do this automatically. Until this is called,
+/// every API in this crate behaves as if there were no apple arguments.
+///
+/// Only the first call has any effect, and later ones are ignored. The arguments are
+/// copied, so `applep` doesn't need to outlive this call.
+///
+/// # Ordering
+///
+/// Nothing stops other code from reading the apple arguments before this runs, and
+/// those reads will see nothing. Call this as early as possible, ideally as the first
+/// thing in `main` (or your own constructor), before spawning threads or calling into
+/// libraries which might use this crate.
+///
+/// # Safety
+///
+/// `applep` must either be null or point to a null-terminated array of pointers to
+/// nul-terminated strings, all of which are valid for reads for the duration of the call.
+///
+/// With the `unsync-read` feature, this must also be called before any thread other
+/// than the calling one could read the apple arguments.
+#[cfg(feature = "no-ctor")]
+pub unsafe fn init(applep: *const *const c_char) {
+    // Safety: The caller upholds the requirements on `applep`.
+    store_args(applep)
+}
+
+/// Tries to capture the apple arguments by finding them in memory, for when this crate's
+/// constructor didn't run.
+///
+/// The constructor can be lost when this crate is linked into a static library and the
+/// linker dead-strips its `__mod_init_func` entry, leaving every API in this crate
+/// without arguments. This is a best-effort way to recover from that, and does nothing
+/// if the arguments were already captured.
+///
+/// Returns `true` if the apple arguments are captured after the call, whether by this
+/// call or earlier.
+///
+/// # Layout
+///
+/// This relies on the classic layout of the initial process stack, in which the loader
+/// places the apple arguments array right after the null terminating the environment
+/// array, and on `environ` still pointing at that original environment array.
+///
+/// # Safety
+///
+/// The process' environment must not have been modified yet, including through
+/// `setenv`, `putenv`, or [`std::env::set_var`], as those can replace `environ` with a
+/// copy that isn't followed by the apple arguments. There's no way to detect that, and
+/// the memory after such a copy would be read as if it were the apple arguments.
+///
+/// With the `unsync-read` feature, this must also be called before any thread other
+/// than the calling one could read the apple arguments.
+pub unsafe fn try_init_from_environ() -> bool {
+    if init_state() != InitState::NotRun {
+        return true;
+    }
+
+    extern "C" {
+        fn _NSGetEnviron() -> *mut *const *const c_char;
+    }
+
+    // Safety: `_NSGetEnviron` always returns a valid pointer to `environ`.
+    let envp = _NSGetEnviron().read();
+    // Safety: The caller promises `environ` is still the loader's array, which is
+    // followed by the apple arguments.
+    store_args(applep_after(envp));
+
+    init_state() != InitState::NotRun
+}
+
+/// Returns the array following the null-terminated `envp` array, which is `applep`
+/// in the initial stack layout, or null if `envp` is null.
+///
+/// # Safety
+///
+/// `envp` must be null or point to a null-terminated array of pointers.
+unsafe fn applep_after(mut envp: *const *const c_char) -> *const *const c_char {
+    if envp.is_null() {
+        return ptr::null();
+    }
+
+    // Safety: The array is null-terminated, so every read up to the null is in bounds.
+    while !envp.read().is_null() {
+        envp = envp.add(1);
+    }
+
+    // Safety: This is one past the terminating null, which is allowed to be computed.
+    envp.add(1)
+}
+
+/// # Safety
+///
+/// `applep` must be null or a valid apple arguments array, see [`init`].
+unsafe fn store_args(mut applep: *const *const c_char) {
+    // Only the first capture is kept, and later ones shouldn't do the work.
+    if STORE_STARTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    // A null `applep` is treated the same as an empty one. Either way something
+    // is published below, marking the arguments as captured.
+    let mut v: Vec<&'static [u8]> = Vec::new();
+    let mut cstrs: Vec<&'static CStr> = Vec::new();
+    let mut indices: Vec<usize> = Vec::new();
+    let mut idx = 0;
+
+    // Safety: `applep` is not null, so its valid to read another pointer from.
+    while !applep.is_null() && !applep.read().is_null() {
+        // Safety: See above
+        let p: *const c_char = applep.read();
+
+        // Safety: `applep` was pointing at a valid nul-terminated
+        // string.
+        let len = strlen(p);
+        let ptr = p as *const u8;
+        let s = core::slice::from_raw_parts(ptr, len + 1); // Keeps the nul.
+
+        if cfg!(feature = "keep-empty-args") || len != 0 {
+            // Every argument is leaked, as it must live for the rest of the program.
+            // Safety: `s` ends at the first nul, which `strlen` found.
+            let arg: &'static CStr = Box::leak(Box::from(CStr::from_bytes_with_nul_unchecked(s)));
+            v.push(arg.to_bytes());
+            cstrs.push(arg);
+            indices.push(idx);
+        }
+
+        // Safety: This will never wrap and after incrementing
+        // past the last array element, the loop will stop.
+        applep = applep.add(1);
+        idx += 1;
+    }
+
+    publish(
+        &CAPTURED,
+        Captured {
+            args: Box::leak(v.into_boxed_slice()),
+            cstrs: Box::leak(cstrs.into_boxed_slice()),
+            indices: Box::leak(indices.into_boxed_slice()),
+        },
+    );
+
+    #[cfg(feature = "init-hook")]
+    hooks::run();
+}
+
+#[cfg(feature = "init-hook")]
+pub use hooks::on_init;
+
+#[cfg(feature = "init-hook")]
+mod hooks {
+    use std::sync::{Mutex, PoisonError};
+
+    /// The callbacks waiting for the arguments, or `None` once they've been run.
+    type Pending = Option<Vec<fn()>>;
+
+    static HOOKS: Mutex<Pending> = Mutex::new(Some(Vec::new()));
+
+    /// Registers `f` to be called as soon as the apple arguments have been captured.
+    ///
+    /// This is only available with the `init-hook` feature. If the arguments were already
+    /// captured, `f` is called immediately instead. Either way it's called exactly once.
+    ///
+    /// Registering from your own static constructor is the only way to run before the
+    /// arguments are captured, as this crate's constructor runs before `main`. Callbacks
+    /// registered that way are called from inside this crate's constructor, in the order
+    /// they were registered. With the `lazy-parse` feature, they're instead called by
+    /// whichever thread reads the arguments first.
+    ///
+    /// # Restrictions
+    ///
+    /// Code running in a static constructor runs before `main`, and possibly before other
+    /// libraries' constructors have run, so it must be careful:
+    /// - Don't rely on anything another constructor sets up, including other crates'.
+    /// - Don't spawn threads or block, as the loader holds a lock while constructors run.
+    /// - Keep it short, as it delays the launch of the whole process.
+    ///
+    /// If `f` panics, the process is aborted, as unwinding out of a constructor isn't
+    /// allowed.
+    pub fn on_init(f: fn()) {
+        let mut hooks = HOOKS.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match hooks.as_mut() {
+            Some(hooks) => hooks.push(f),
+            None => {
+                drop(hooks);
+                call(f);
+            }
+        }
+    }
+
+    /// Calls every registered callback, after the arguments have been stored.
+    pub(super) fn run() {
+        let hooks = HOOKS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .unwrap_or_default();
+
+        // The lock isn't held, so callbacks can register more callbacks.
+        for f in hooks {
+            call(f);
+        }
+    }
+
+    fn call(f: fn()) {
+        struct AbortOnUnwind;
+
+        impl Drop for AbortOnUnwind {
+            fn drop(&mut self) {
+                std::process::abort();
+            }
+        }
+
+        let guard = AbortOnUnwind;
+        f();
+        core::mem::forget(guard);
+    }
+}
+
+/// With `lazy-parse`, the constructor only stashes `applep`, and the arguments are
+/// copied out of it the first time they're read.
+///
+/// This moves the work out of the load-time constructor and onto the first reader,
+/// which some programs prefer for startup latency.
+#[cfg(all(feature = "lazy-parse", not(feature = "no-ctor")))]
+mod lazy {
+    use core::ptr;
+    use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+    use std::os::raw::c_char;
+    use std::sync::Once;
+
+    static APPLEP: AtomicPtr<*const c_char> = AtomicPtr::new(ptr::null_mut());
+    static STASHED: AtomicBool = AtomicBool::new(false);
+
+    /// # Safety
+    ///
+    /// `applep` must be null or a valid apple arguments array, which stays valid for the
+    /// rest of the program.
+    pub(super) unsafe fn stash(applep: *const *const c_char) {
+        // `Relaxed` is fine because the store of `STASHED` with
+        // `Release` acts as a fence.
+        APPLEP.store(applep.cast_mut(), Ordering::Relaxed);
+        STASHED.store(true, Ordering::Release);
+    }
+
+    /// Copies the stashed arguments, if there are any and they haven't been yet.
+    ///
+    /// Concurrent callers wait for the first one to finish, so none of them can see the
+    /// arguments half copied.
+    #[cold]
+    pub(super) fn parse() {
+        static PARSE: Once = Once::new();
+
+        if !STASHED.load(Ordering::Acquire) {
+            return;
+        }
+
+        PARSE.call_once(|| {
+            let applep = APPLEP.load(Ordering::Relaxed);
+            // Safety: The loader's `applep` and the strings it points to live on the
+            // initial stack of the process, which is never freed.
+            unsafe { super::store_args(applep) }
+        });
+    }
+}
+
+/// Miri doesn't pass the constructor any arguments, so this stands in for it by
+/// capturing a fixed set of synthetic arguments the first time they're read.
+///
+/// Under Miri, the crate and anything using it only ever sees these, not real apple
+/// arguments. They still go through the same capturing and reading code, so that
+/// code's unsafety can be checked.
+#[cfg(all(miri, not(feature = "no-ctor")))]
+mod miri {
+    use std::os::raw::c_char;
+    use std::sync::Once;
+
+    const ARGS: [&[u8]; 6] = [
+        b"executable_path=/miri/appleargs\0",
+        b"\0",
+        b"th_port=0x103\0",
+        b"executable_cdhash=acd984a2fa40d1b36ba71094e7c0318a6bf15084\0",
+        b"arm64e_abi=os\0",
+        b"ptr_munge=\0",
+    ];
+
+    pub(super) fn init() {
+        static INIT: Once = Once::new();
+
+        INIT.call_once(|| {
+            let mut applep: Vec<*const c_char> = ARGS.iter().map(|s| s.as_ptr().cast()).collect();
+            applep.push(core::ptr::null());
+
+            // Safety: `applep` is a null-terminated array of nul-terminated strings.
+            #[cfg(not(feature = "lazy-parse"))]
+            unsafe {
+                super::store_args(applep.as_ptr())
+            }
+
+            // Leaked, as the real `applep` is never freed either.
+            // Safety: `applep` is a null-terminated array of nul-terminated strings.
+            #[cfg(feature = "lazy-parse")]
+            unsafe {
+                super::lazy::stash(Vec::leak(applep).as_ptr())
+            }
+        });
+    }
+}
+
+#[cfg(not(feature = "internal-strlen"))]
+extern "C" {
+    /// Provided by libc or compiler_builtins.
+    fn strlen(s: *const c_char) -> usize;
+}
+
+/// Returns the length of the nul-terminated string `s`, without linking to libc's.
+///
+/// This only runs while capturing the arguments, once per process, so it isn't worth
+/// optimizing further.
+///
+/// # Safety
+///
+/// `s` must point to a nul-terminated string which is valid for reads.
+#[cfg(feature = "internal-strlen")]
+unsafe fn strlen(s: *const c_char) -> usize {
+    let mut len = 0;
+    // Safety: The caller guarantees the string is nul-terminated, so every byte up to
+    // and including the nul is valid to read.
+    while *s.add(len) != 0 {
+        len += 1;
+    }
+    len
+}
+
+// Miri runs constructors, but without passing them any arguments.
+#[cfg(not(any(feature = "no-ctor", miri)))]
+unsafe extern "C" fn init_function(
+    _argc: c_int,
+    _argv: *const *const c_char,
+    _envp: *const *const c_char,
+    applep: *const *const c_char,
+) {
+    // Safety: The loader always passes a valid `applep`, and it's never freed.
+    #[cfg(feature = "lazy-parse")]
+    lazy::stash(applep);
+
+    // Safety: The loader always passes a valid `applep`.
+    #[cfg(not(feature = "lazy-parse"))]
+    store_args(applep)
+}
+
+// All of these are Mach-O based and run the pointers placed in this section
+// at load time. A new Apple OS must be checked before being added here.
+//
+// Mach-O has no constructor priorities, so there's no way to run before other
+// constructors in the same image. That doesn't matter for `std::env::args`, which
+// reads `_NSGetArgv` on demand and is usable from any constructor.
+//
+// Mac Catalyst is covered by `ios`, as it's `target_os = "ios"` with
+// `target_abi = "macabi"`, and uses the same section.
+#[cfg(not(any(feature = "no-ctor", miri)))]
+#[used]
+#[cfg_attr(
+    any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "tvos",
+        target_os = "watchos",
+        target_os = "visionos"
+    ),
+    link_section = "__DATA,__mod_init_func"
+)]
+static CTOR: unsafe extern "C" fn(
+    argc: c_int,
+    argv: *const *const c_char,
+    envp: *const *const c_char,
+    applep: *const *const c_char,
+) = init_function;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "no-ctor")]
+    use std::os::unix::prelude::OsStrExt;
+
+    #[test]
+    fn string_lengths() {
+        for s in [&b"\0"[..], b"a\0", b"executable_path=/bin/ls\0", b"a\0b\0"] {
+            // Safety: Each string is nul-terminated.
+            let len = unsafe { strlen(s.as_ptr().cast()) };
+            assert_eq!(len, s.iter().position(|&b| b == 0).unwrap());
+        }
+    }
+
+    #[test]
+    fn environ_walk() {
+        let strings: [&[u8]; 4] = [
+            b"HOME=/\0",
+            b"PATH=/bin\0",
+            b"executable_path=/bin/ls\0",
+            b"\0",
+        ];
+        let [home, path, exe, empty] = strings.map(|s| s.as_ptr().cast::<c_char>());
+        let block = [home, path, ptr::null(), exe, empty, ptr::null()];
+
+        // Safety: `block` starts with a null-terminated array.
+        unsafe {
+            assert_eq!(applep_after(block.as_ptr()), block[3..].as_ptr());
+            assert_eq!(applep_after(block[2..].as_ptr()), block[3..].as_ptr());
+            assert!(applep_after(ptr::null()).is_null());
+        }
+    }
+
+    #[test]
+    fn concurrent_publish() {
+        const READERS: usize = 4;
+        static SLOT: AtomicPtr<Captured> = AtomicPtr::new(ptr::null_mut());
+        static SEEN: AtomicUsize = AtomicUsize::new(0);
+
+        let args: &'static [&'static [u8]] = &[b"a=1", b"b=2", b"c=3"];
+        let cstrs: &'static [&'static CStr] = &[c"a=1", c"b=2", c"c=3"];
+        let indices: &'static [usize] = &[0, 2, 3];
+
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    SEEN.fetch_add(1, Ordering::Relaxed);
+                    let captured = loop {
+                        if let Some(captured) = load_from(&SLOT, Ordering::Acquire) {
+                            break captured;
+                        }
+                        std::thread::yield_now();
+                    };
+                    assert_eq!(captured.args, args);
+                    assert_eq!(captured.cstrs, cstrs);
+                    assert_eq!(captured.indices, indices);
+                })
+            })
+            .collect();
+
+        // Give the readers a chance to start spinning first.
+        while SEEN.load(Ordering::Relaxed) < READERS {
+            std::thread::yield_now();
+        }
+        assert!(load_from(&SLOT, Ordering::Acquire).is_none());
+        publish(
+            &SLOT,
+            Captured {
+                args,
+                cstrs,
+                indices,
+            },
+        );
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    /// Checks every interleaving of a capture being published while it's read.
+    ///
+    /// Run these with `RUSTFLAGS="--cfg loom" cargo test --release --lib loom`. loom only
+    /// tracks its own atomics, so the plain writes of the arguments themselves are
+    /// checked by running `concurrent_publish` under Miri instead.
+    #[cfg(loom)]
+    mod loom {
+        use super::*;
+        use ::loom::sync::atomic::AtomicPtr;
+        use ::loom::sync::Arc;
+
+        const ARGS: &[&[u8]] = &[b"a=1", b"b=2"];
+        const CSTRS: &[&CStr] = &[c"a=1", c"b=2"];
+        const INDICES: &[usize] = &[0, 2];
+
+        fn model(order: Ordering) {
+            ::loom::model(move || {
+                let slot = Arc::new(AtomicPtr::new(ptr::null_mut()));
+
+                let readers: Vec<_> = (0..2)
+                    .map(|_| {
+                        let slot = Arc::clone(&slot);
+                        ::loom::thread::spawn(move || {
+                            load_from(&*slot, order).map(|c| (c.args, c.indices))
+                        })
+                    })
+                    .collect();
+
+                publish(
+                    &*slot,
+                    Captured {
+                        args: ARGS,
+                        cstrs: CSTRS,
+                        indices: INDICES,
+                    },
+                );
+
+                for reader in readers {
+                    if let Some((args, indices)) = reader.join().unwrap() {
+                        assert_eq!(args, ARGS);
+                        assert_eq!(indices, INDICES);
+                    }
+                }
+                assert!(load_from(&*slot, order).is_some());
+            });
+        }
+
+        #[test]
+        fn loom_publish_acquire() {
+            model(Ordering::Acquire);
+        }
+
+        // `unsync-read` only supports reads ordered after the publish by other means, which
+        // this doesn't model, so its `Relaxed` loads racing the publish aren't checked.
+        #[test]
+        #[cfg(not(all(feature = "unsync-read", not(feature = "lazy-parse"))))]
+        fn loom_publish_read_ordering() {
+            model(READ_ORDERING);
+        }
+    }
+
+    /// How many arguments `std::env::args` had in [`probe_ctor`], or `usize::MAX` if it
+    /// never ran.
+    static PROBE_ARGC: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+    /// A constructor of its own, which the loader runs just like the crate's, to observe
+    /// the Rust runtime's argument setup from a constructor without changing what the
+    /// crate's constructor does.
+    extern "C" fn probe_ctor() {
+        PROBE_ARGC.store(std::env::args_os().len(), Ordering::Relaxed);
+    }
+
+    #[used]
+    #[cfg_attr(target_vendor = "apple", link_section = "__DATA,__mod_init_func")]
+    static PROBE_CTOR: extern "C" fn() = probe_ctor;
+
+    #[test]
+    #[cfg_attr(
+        not(target_vendor = "apple"),
+        ignore = "constructors only run on Apple targets"
+    )]
+    fn constructors_see_std_args() {
+        let argc = PROBE_ARGC.load(Ordering::Relaxed);
+        assert_eq!(argc, std::env::args_os().len());
+    }
+
+    #[test]
+    #[cfg(feature = "no-ctor")]
+    fn manual_init() {
+        assert!(args_slice().is_empty());
+        assert_eq!(init_state(), InitState::NotRun);
+
+        #[cfg(feature = "init-hook")]
+        static HOOK_SAW: AtomicUsize = AtomicUsize::new(0);
+        #[cfg(feature = "init-hook")]
+        on_init(|| HOOK_SAW.store(args_slice().len() + 1, Ordering::Relaxed));
+
+        let strings: [&[u8]; 3] = [b"executable_path=/bin/ls\0", b"\0", b"th_port=259\0"];
+        let mut applep: Vec<*const c_char> = strings.iter().map(|s| s.as_ptr().cast()).collect();
+        applep.push(ptr::null());
+
+        // Safety: `applep` is a null-terminated array of valid strings.
+        unsafe { init(applep.as_ptr()) };
+
+        let (expected, indices): (&[&[u8]], &[usize]) = if cfg!(feature = "keep-empty-args") {
+            (
+                &[b"executable_path=/bin/ls", b"", b"th_port=259"],
+                &[0, 1, 2],
+            )
+        } else {
+            (&[b"executable_path=/bin/ls", b"th_port=259"], &[0, 2])
+        };
+        assert_eq!(args_slice(), expected);
+        assert_eq!(args_indices(), indices);
+        for (cstr, arg) in args_cstrs().iter().zip(args_slice()) {
+            assert!(ptr::eq(cstr.to_bytes(), *arg));
+            assert_eq!(cstr.to_bytes_with_nul().last(), Some(&0));
+        }
+        assert_eq!(args_cstrs().len(), expected.len());
+        assert_eq!(init_state(), InitState::Ran(expected.len()));
+        assert_eq!(crate::env::count_keys(), 2);
+
+        #[cfg(feature = "init-hook")]
+        {
+            assert_eq!(HOOK_SAW.load(Ordering::Relaxed), expected.len() + 1);
+            on_init(|| HOOK_SAW.store(0, Ordering::Relaxed));
+            assert_eq!(HOOK_SAW.load(Ordering::Relaxed), 0);
+        }
+
+        let mut indexed = crate::apple_args_os().indexed();
+        assert_eq!(indexed.next_back().map(|(i, _)| i), Some(2));
+        assert_eq!(
+            indexed.next().map(|(i, arg)| (i, arg.as_bytes())),
+            Some((0, &b"executable_path=/bin/ls"[..]))
+        );
+        assert_eq!(indexed.len(), expected.len() - 2);
+
+        let mut args = crate::apple_args_os();
+        args.next();
+        assert_eq!(
+            args.indexed().map(|(i, _)| i).collect::<Vec<_>>(),
+            indices[1..]
+        );
+
+        // Later calls are ignored.
+        // Safety: A null `applep` is allowed.
+        unsafe { init(ptr::null()) };
+        assert_eq!(args_slice().len(), expected.len());
+    }
+}
@@ -0,0 +1,139 @@
+//! Helpers for testing code built on this crate.
+//!
+//! The property testing strategies are only available with the `proptest` feature, and
+//! [`fixtures`] has sample arguments to run parsers against without a device.
+//!
+//! # Device runs
+//!
+//! [`check_apple_args`] is meant for integration tests run on a device or
+//! simulator, where the loader is the only thing that can be checked. Those work best as
+//! a test binary with `harness = false`, whose `main` returns the result:
+//!
+//! ```no_run
+//! fn main() -> Result<(), appleargs::testing::NoAppleArgs> {
+//!     let count = appleargs::testing::check_apple_args()?;
+//!     println!("found {count} apple arguments");
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The binary then exits with status 0 if the arguments were captured, and 1 if not, so
+//! a runner launching it should only check its exit status instead of scraping its
+//! output. On a simulator, `xcrun simctl spawn` forwards the exit status of what it
+//! runs, unlike `xcrun simctl launch`.
+
+use crate::{init_state, InitState};
+
+#[cfg(feature = "bench-util")]
+use std::ffi::CString;
+
+pub mod fixtures;
+
+#[cfg(feature = "proptest")]
+mod arb;
+#[cfg(feature = "proptest")]
+pub use arb::arb_apple_args;
+
+/// Checks that this process' apple arguments were captured and that there was at
+/// least one, returning how many there were.
+///
+/// # Errors
+///
+/// Returns an error describing what went wrong if there are no apple arguments.
+pub fn check_apple_args() -> Result<usize, NoAppleArgs> {
+    check(init_state())
+}
+
+fn check(state: InitState) -> Result<usize, NoAppleArgs> {
+    match state {
+        InitState::Ran(count) => Ok(count),
+        state => Err(NoAppleArgs { state }),
+    }
+}
+
+/// Returns `count` made up apple arguments, for benchmarking with more arguments than
+/// the loader passes.
+///
+/// This is only available with the `bench-util` feature. The arguments are the same on
+/// every call. The first is always an `executable_path`, and the rest are mostly
+/// `synthetic_key_N=0x...` pairs, with every eighth one a token without an `=`. Passing
+/// them to `init` with the `no-ctor` feature makes the whole crate see
+/// them instead of the real ones.
+#[cfg(feature = "bench-util")]
+pub fn synthetic_args(count: usize) -> Vec<CString> {
+    (0..count)
+        .map(|i| {
+            let arg = match i {
+                0 => String::from("executable_path=/synthetic/appleargs"),
+                i if i % 8 == 7 => format!("synthetic_token_{i}"),
+                i => format!(
+                    "synthetic_key_{i}=0x{:016x}",
+                    (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                ),
+            };
+            // None of the formatted strings contain a nul.
+            CString::new(arg).expect("no nuls")
+        })
+        .collect()
+}
+
+/// The error returned by [`check_apple_args`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoAppleArgs {
+    state: InitState,
+}
+
+impl NoAppleArgs {
+    /// Returns the state the arguments were in when checked, which is never
+    /// [`InitState::Ran`].
+    pub fn state(&self) -> InitState {
+        self.state
+    }
+}
+
+impl core::fmt::Display for NoAppleArgs {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self.state {
+            InitState::NotRun => "the apple arguments were never captured",
+            _ => "the apple arguments were captured, but there weren't any",
+        })
+    }
+}
+
+impl std::error::Error for NoAppleArgs {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_state() {
+        assert_eq!(check(InitState::Ran(3)), Ok(3));
+
+        let err = check(InitState::RanEmpty).unwrap_err();
+        assert_eq!(err.state(), InitState::RanEmpty);
+        assert!(err.to_string().contains("weren't any"));
+        assert_eq!(
+            check(InitState::NotRun).unwrap_err().to_string(),
+            "the apple arguments were never captured"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "bench-util")]
+    fn synthetic() {
+        let args = synthetic_args(17);
+
+        assert_eq!(args.len(), 17);
+        assert_eq!(args, synthetic_args(17));
+        assert_eq!(args[0].to_bytes(), b"executable_path=/synthetic/appleargs");
+        assert_eq!(args[7].to_bytes(), b"synthetic_token_7");
+        assert!(args[1].to_bytes().starts_with(b"synthetic_key_1=0x"));
+
+        let pairs = args
+            .iter()
+            .filter(|arg| crate::env::split_kv(arg.to_bytes()).is_some());
+        assert_eq!(pairs.count(), 15);
+        assert!(synthetic_args(0).is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+//! Proptest strategies for apple argument arrays.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+/// Keys the kernel is known to pass, to make generated arrays look realistic.
+const KNOWN_KEYS: &[&str] = &[
+    "executable_path",
+    "ptr_munge",
+    "main_stack",
+    "executable_file",
+    "dyld_file",
+    "executable_cdhash",
+    "executable_boothash",
+    "arm64e_abi",
+    "th_port",
+    "stack_guard",
+    "malloc_entropy",
+];
+
+/// Returns a strategy generating synthetic apple argument arrays, shaped like what the
+/// loader passes.
+///
+/// Each array has up to 16 arguments, none of which contain a nul. Every argument is
+/// one of:
+/// - A `key=value` pair, most of the time. Keys are either well known ones, 1 to 20
+///   lowercase ASCII letters and underscores, or 1 to 20 arbitrary bytes, which may not
+///   be UTF-8 but never contain an `=`. Values are 0 to 64 arbitrary bytes, which may
+///   contain more `=`s.
+/// - A token without an `=`, of 1 to 32 arbitrary bytes.
+/// - An empty string, which this crate drops when capturing real arguments.
+pub fn arb_apple_args() -> impl Strategy<Value = Vec<Vec<u8>>> {
+    vec(arb_arg(), 0..=16)
+}
+
+fn arb_arg() -> impl Strategy<Value = Vec<u8>> {
+    let pair = (arb_key(), arb_bytes(0..=64)).prop_map(|(k, v)| [k, b"=".to_vec(), v].concat());
+
+    prop_oneof![
+        6 => pair,
+        2 => arb_bytes(1..=32).prop_map(without_eq),
+        1 => Just(Vec::new()),
+    ]
+}
+
+fn arb_key() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        select(KNOWN_KEYS).prop_map(|k| k.as_bytes().to_vec()),
+        "[a-z_]{1,20}".prop_map(String::into_bytes),
+        arb_bytes(1..=20).prop_map(without_eq),
+    ]
+}
+
+fn arb_bytes(len: core::ops::RangeInclusive<usize>) -> impl Strategy<Value = Vec<u8>> {
+    vec(1u8..=u8::MAX, len)
+}
+
+fn without_eq(mut bytes: Vec<u8>) -> Vec<u8> {
+    for b in &mut bytes {
+        if *b == b'=' {
+            *b = b'-';
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn generated_args_are_valid(args in arb_apple_args()) {
+            prop_assert!(args.len() <= 16);
+
+            for arg in &args {
+                prop_assert!(!arg.contains(&0));
+                if let Some((key, _)) = crate::env::split_kv(arg) {
+                    prop_assert!(!key.is_empty());
+                }
+            }
+        }
+    }
+}
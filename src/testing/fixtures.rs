@@ -0,0 +1,61 @@
+//! Sample apple argument arrays, shaped like real launches.
+//!
+//! Each sample lists the arguments in the order the kernel passes them, as this crate
+//! captures them: the empty strings are dropped, and the values libSystem erases before
+//! any constructor runs, like `ptr_munge`, are left empty. The keys and the format of
+//! every value are taken from real launches, but paths, hashes, and inode numbers are
+//! made up, so nothing in them identifies a real machine or executable.
+
+/// A command line executable launched from a shell on an Apple silicon Mac.
+pub fn macos_sample() -> &'static [&'static [u8]] {
+    &[
+        b"executable_path=/Users/me/project/target/debug/app",
+        b"ptr_munge=",
+        b"main_stack=",
+        b"executable_file=0x1a01000010,0x2b6c1d4",
+        b"dyld_file=0x1a0100000f,0xfffffff000dc897",
+        b"executable_cdhash=acd984a2fa40d1b36ba71094e7c0318a6bf15084",
+        b"executable_boothash=9f2bd8e7b7a6d3a58e755b6c4b6d8e2251ce2f3c",
+        b"arm64e_abi=os",
+        b"th_port=0x103",
+    ]
+}
+
+/// An app launched from the home screen of an iOS device.
+pub fn ios_device_sample() -> &'static [&'static [u8]] {
+    &[
+        b"executable_path=/private/var/containers/Bundle/Application/4C1F2A3E-8B1D-4F0A-9E6C-2D7B5A1C3F90/App.app/App",
+        b"ptr_munge=",
+        b"main_stack=",
+        b"executable_file=0x1a01000004,0x9c0b1e",
+        b"dyld_file=0x1a01000004,0xfffffff0000a5c8",
+        b"executable_cdhash=5be7a6d0c3f4e2b1a09f8e7d6c5b4a3928170f6e",
+        b"executable_boothash=0e1d2c3b4a5968778695a4b3c2d1e0f1f2e3d4c5",
+        b"arm64e_abi=os",
+        b"th_port=0x103",
+    ]
+}
+
+/// An app launched in the iOS Simulator, which runs on the Mac's own kernel.
+pub fn simulator_sample() -> &'static [&'static [u8]] {
+    &[
+        b"executable_path=/Users/me/Library/Developer/CoreSimulator/Devices/0B7E5A2C-6D3F-4E1B-A8C9-7F2D1E0B3A46/data/Containers/Bundle/Application/91D3C5E7-2A4B-4C6D-8E0F-1A3B5C7D9E2F/App.app/App",
+        b"ptr_munge=",
+        b"main_stack=",
+        b"executable_file=0x1a01000010,0x3d81a92",
+        b"dyld_file=0x1a0100000f,0xfffffff000dc897",
+        b"executable_cdhash=c0ffee0ddba11fa11b0a710ade5ca1ab1efee1ed",
+        b"executable_boothash=1f3e5d7c9b0a28466482a0c1e3f5d7b9ad8c6e4f",
+        b"arm64e_abi=os",
+        b"th_port=0x103",
+    ]
+}
+
+/// Every sample, along with its name.
+pub fn all_samples() -> [(&'static str, &'static [&'static [u8]]); 3] {
+    [
+        ("macos", macos_sample()),
+        ("ios_device", ios_device_sample()),
+        ("simulator", simulator_sample()),
+    ]
+}
@@ -0,0 +1,17 @@
+//! Checks the apple arguments were captured, reporting the result through the exit
+//! status so device and simulator runs don't depend on scraping the output.
+
+use appleargs::testing::{check_apple_args, NoAppleArgs};
+
+fn main() -> Result<(), NoAppleArgs> {
+    // Nothing else captures them with `no-ctor`, and the environment is untouched.
+    // Safety: Nothing has modified the environment or spawned threads yet.
+    #[cfg(feature = "no-ctor")]
+    unsafe {
+        appleargs::try_init_from_environ();
+    }
+
+    let count = check_apple_args()?;
+    println!("found {count} apple arguments");
+    Ok(())
+}
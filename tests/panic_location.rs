@@ -0,0 +1,54 @@
+//! Checks that invalid UTF-8 panics point at the caller.
+//!
+//! This replaces the process' panic hook to see where each panic happened, so it lives
+//! in its own binary without the test harness, where no other test can panic at the
+//! same time.
+
+use std::os::raw::c_char;
+use std::panic;
+use std::ptr;
+use std::sync::Mutex;
+
+static LOCATIONS: Mutex<Vec<(String, u32)>> = Mutex::new(Vec::new());
+
+fn main() {
+    let strings: [&[u8]; 2] = [b"a=1\0", b"\xff\0"];
+    let mut applep: Vec<*const c_char> = strings.iter().map(|s| s.as_ptr().cast()).collect();
+    applep.push(ptr::null());
+
+    // Safety: `applep` is a null-terminated array of nul-terminated strings, and no
+    // other threads exist yet.
+    unsafe { appleargs::init(applep.as_ptr()) };
+
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(|info| {
+        let location = info.location().unwrap();
+        LOCATIONS
+            .lock()
+            .unwrap()
+            .push((location.file().to_owned(), location.line()));
+    }));
+
+    let mut iter = appleargs::apple_args();
+    let next_line = line!() + 1;
+    let first = panic::catch_unwind(move || (iter.next(), iter.next()));
+
+    let iter = appleargs::apple_args();
+    let loop_line = line!() + 1;
+    let looped = panic::catch_unwind(move || for _ in iter {});
+
+    let mut iter = appleargs::apple_args();
+    let back_line = line!() + 1;
+    let back = panic::catch_unwind(move || iter.next_back());
+
+    panic::set_hook(previous);
+    assert!(first.is_err() && looped.is_err() && back.is_err());
+
+    let locations = LOCATIONS.lock().unwrap();
+    for line in [next_line, loop_line, back_line] {
+        assert!(
+            locations.contains(&(file!().to_owned(), line)),
+            "{locations:?}"
+        );
+    }
+}
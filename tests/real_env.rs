@@ -0,0 +1,70 @@
+//! Tests which modify the real environment.
+//!
+//! Modifying the environment while another thread reads it is undefined behavior with
+//! Apple's libc, so these live in their own binary without the test harness and run
+//! one after another on the main thread.
+
+use std::borrow::Cow;
+use std::os::raw::c_char;
+use std::ptr;
+
+fn main() {
+    let strings: [&[u8]; 2] = [
+        b"APPLEARGS_TEST_FALLBACK=from_apple\0",
+        b"APPLEARGS_TEST_CLAP=from_apple\0",
+    ];
+    let mut applep: Vec<*const c_char> = strings.iter().map(|s| s.as_ptr().cast()).collect();
+    applep.push(ptr::null());
+
+    // Safety: `applep` is a null-terminated array of valid strings, and no other
+    // threads exist yet.
+    unsafe { appleargs::init(applep.as_ptr()) };
+
+    var_fallback();
+    #[cfg(feature = "clap")]
+    clap_defaults();
+}
+
+fn var_fallback() {
+    use appleargs::env::var_with_fallback;
+
+    std::env::set_var("APPLEARGS_TEST_FALLBACK", "from_env");
+    std::env::set_var("APPLEARGS_TEST_ENV_ONLY", "from_env");
+
+    assert_eq!(
+        var_with_fallback("APPLEARGS_TEST_FALLBACK"),
+        Some(Cow::Borrowed("from_apple"))
+    );
+    assert_eq!(
+        var_with_fallback("APPLEARGS_TEST_ENV_ONLY"),
+        Some(Cow::Owned("from_env".into()))
+    );
+    assert_eq!(var_with_fallback("APPLEARGS_TEST_MISSING"), None);
+}
+
+#[cfg(feature = "clap")]
+fn clap_defaults() {
+    use clap::{Arg, Command};
+
+    let build = || {
+        let cmd = Command::new("test").arg(Arg::new("a").long("a").env("APPLEARGS_TEST_CLAP"));
+        appleargs::env::with_clap_defaults(cmd)
+    };
+    let get = |matches: clap::ArgMatches| matches.get_one::<String>("a").cloned();
+
+    assert_eq!(
+        get(build().get_matches_from(["test"])).as_deref(),
+        Some("from_apple")
+    );
+
+    // Real environment variables take precedence over apple arguments.
+    std::env::set_var("APPLEARGS_TEST_CLAP", "from_env");
+    assert_eq!(
+        get(build().get_matches_from(["test"])).as_deref(),
+        Some("from_env")
+    );
+    assert_eq!(
+        get(build().get_matches_from(["test", "--a", "from_cli"])).as_deref(),
+        Some("from_cli")
+    );
+}